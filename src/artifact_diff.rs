@@ -0,0 +1,122 @@
+use clap::ValueEnum;
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffFormat {
+    /// JSON lines when every line of both versions is a JSON object, lines otherwise
+    Auto,
+    /// Unified diff of the lines
+    Lines,
+    /// Records added and removed, regardless of their order
+    Jsonl,
+}
+
+/// Compares two versions of an artifact.
+///
+/// Line diffs are unified diffs with `from` and `to` as headers. JSON lines
+/// diffs list every removed record prefixed with `-` followed by every added
+/// record prefixed with `+`, with object keys sorted so that key order does
+/// not count as a change.
+pub fn diff(
+    from_content: &str,
+    to_content: &str,
+    format: DiffFormat,
+    from: &str,
+    to: &str,
+) -> Result<String, String> {
+    let jsonl = match format {
+        DiffFormat::Lines => false,
+        DiffFormat::Jsonl => true,
+        DiffFormat::Auto => {
+            let from_records = parse_records(from_content);
+            let to_records = parse_records(to_content);
+            matches!((&from_records, &to_records), (Ok(a), Ok(b)) if !a.is_empty() || !b.is_empty())
+        }
+    };
+
+    if !jsonl {
+        return Ok(similar::TextDiff::from_lines(from_content, to_content)
+            .unified_diff()
+            .header(from, to)
+            .to_string());
+    }
+
+    let from_records = parse_records(from_content).map_err(|err| format!("{from}: {err}"))?;
+    let to_records = parse_records(to_content).map_err(|err| format!("{to}: {err}"))?;
+
+    let mut out = String::new();
+    for record in from_records.difference(&to_records) {
+        out.push_str(&format!("- {record}\n"));
+    }
+    for record in to_records.difference(&from_records) {
+        out.push_str(&format!("+ {record}\n"));
+    }
+    Ok(out)
+}
+
+/// Parses every non-empty line as a JSON object, serialized back with sorted keys.
+fn parse_records(content: &str) -> Result<BTreeSet<String>, String> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(i, line)| match serde_json::from_str::<Value>(line) {
+            Ok(value @ Value::Object(_)) => Ok(value.to_string()),
+            Ok(_) => Err(format!("line {} is not a JSON object", i + 1)),
+            Err(err) => Err(format!("line {}: {err}", i + 1)),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines_diff() {
+        let out = diff(
+            "a.example.com\nb.example.com\n",
+            "a.example.com\nc.example.com\n",
+            DiffFormat::Auto,
+            "from",
+            "to",
+        )
+        .unwrap_or_else(|err| panic!("expected ok, got {err}"));
+
+        assert!(out.starts_with("--- from\n+++ to\n"), "got {out}");
+        assert!(out.contains("-b.example.com\n"), "got {out}");
+        assert!(out.contains("+c.example.com\n"), "got {out}");
+    }
+
+    #[test]
+    fn test_jsonl_diff() {
+        let from = r#"{"host":"a.example.com","port":443}
+{"host":"b.example.com","port":80}
+"#;
+        let to = r#"{"port":443,"host":"a.example.com"}
+
+{"host":"c.example.com","port":8080}
+"#;
+
+        let out = diff(from, to, DiffFormat::Auto, "from", "to")
+            .unwrap_or_else(|err| panic!("expected ok, got {err}"));
+        assert_eq!(
+            out,
+            "- {\"host\":\"b.example.com\",\"port\":80}\n+ {\"host\":\"c.example.com\",\"port\":8080}\n"
+        );
+    }
+
+    #[test]
+    fn test_jsonl_diff_invalid() {
+        let result = diff("{}\n", "not json\n", DiffFormat::Jsonl, "from", "to");
+        assert!(result.is_err(), "expected error, got {result:?}");
+
+        let out = diff("{}\n", "[1]\n", DiffFormat::Auto, "from", "to")
+            .unwrap_or_else(|err| panic!("expected ok, got {err}"));
+        assert!(
+            out.starts_with("--- from"),
+            "expected a lines diff, got {out}"
+        );
+    }
+}
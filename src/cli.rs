@@ -1,20 +1,40 @@
-use crate::client::{Client, Error, HTTPClient};
-use crate::validation;
-use clap::{Args, CommandFactory, Parser, Subcommand, ValueHint};
+use crate::artifact_diff::{self, DiffFormat};
+use crate::client::{BlobInfo, BlobPage, Client, Error, HTTPClient, RunnerInfo, UploadOptions};
+use crate::compression::ContentEncoding;
+use crate::config::{self, Config};
+use crate::download_cache::{self, DownloadCache};
+use crate::progress::Progress;
+use crate::{extract, local_run, multipart, remote_zip, template, transfer, validation, workflow};
+use clap::builder::{PossibleValue, PossibleValuesParser};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum, ValueHint};
 use clap_complete::{Shell, generate};
+use indicatif::HumanBytes;
 use serde_json::Value;
 use std::collections::BTreeMap;
+use std::io::{BufRead, IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
-use std::{env, fs, io};
+use std::{env, fs, io, thread};
 use uuid::Uuid;
 
 type Result<T> = std::result::Result<T, String>;
 
+/// File argument standing for stdin or stdout.
+const STDIO: &str = "-";
+
 #[derive(Parser, Debug)]
 #[command(version, about)]
 pub struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+
+    /// do not report the progress of file transfers on stderr
+    #[arg(long, global = true)]
+    no_progress: bool,
+
+    /// limit file transfers to a rate in bytes per second, with an optional K, M or G suffix,
+    /// defaulting to limitRate in ~/.config/bh/config.json
+    #[arg(long, global = true, env = "BOUNTYHUB_LIMIT_RATE", value_parser = parse_size)]
+    limit_rate: Option<u64>,
 }
 
 impl Cli {
@@ -22,7 +42,7 @@ impl Cli {
         let cli = Cli::parse();
 
         match cli.command {
-            Some(command) => command.run()?,
+            Some(command) => command.run(&Progress::new(!cli.no_progress), cli.limit_rate)?,
             None => {
                 Cli::command().print_help().expect("Failed to print help");
             }
@@ -33,7 +53,8 @@ impl Cli {
 }
 
 /// Commands rely on `BOUNTYHUB_TOKEN` and `BOUNTYHUB_URL`
-/// environment variables.
+/// environment variables. Encrypted blobs additionally need
+/// `BOUNTYHUB_IDENTITY_FILE` pointing to an age identity file.
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Job related commands
@@ -44,6 +65,10 @@ enum Commands {
     #[command(subcommand)]
     Scan(Scan),
 
+    /// Workflow related commands
+    #[command(subcommand)]
+    Workflow(Workflow),
+
     /// Blob related commands
     #[command(subcommand)]
     Blob(Blob),
@@ -56,6 +81,15 @@ enum Commands {
     #[command(subcommand)]
     Bhlast(Bhlast),
 
+    /// Download cache related commands
+    ///
+    /// Blobs and artifacts are kept in ~/.cache/bh/downloads once downloaded, and
+    /// downloaded again only when they changed. The cache keeps to cacheMaxSize in
+    /// ~/.config/bh/config.json, 1G by default, removing the least recently used
+    /// files first.
+    #[command(subcommand)]
+    Cache(Cache),
+
     /// Markdown related commands
     #[command(subcommand)]
     Md(Md),
@@ -66,21 +100,31 @@ enum Commands {
 }
 
 impl Commands {
-    fn run(self) -> Result<()> {
+    fn run(self, progress: &Progress, limit_rate: Option<u64>) -> Result<()> {
+        let new_client = || new_client(limit_rate);
         match self {
             Commands::Md(md) => md.run()?,
+            Commands::Cache(cache) => cache.run()?,
             Commands::Completion(completion) => completion.run()?,
             Commands::Job(job) => {
                 let client = new_client()?;
-                job.run(client)?
+                job.run(client, progress, &mut io::stdout())?
             }
             Commands::Scan(scan) => {
                 let client = new_client()?;
                 scan.run(client)?
             }
+            Commands::Workflow(Workflow::Validate(validate)) => validate.run()?,
+            Commands::Workflow(Workflow::Graph(graph)) => graph.run(new_client)?,
+            Commands::Workflow(Workflow::Init(init)) => init.run()?,
+            Commands::Workflow(Workflow::RunLocal(run_local)) => run_local.run()?,
+            Commands::Workflow(workflow) => {
+                let client = new_client()?;
+                workflow.run(client)?
+            }
             Commands::Runner(runner) => {
                 let client = new_client()?;
-                runner.run(client)?
+                runner.run(client, &mut io::stdout())?
             }
             Commands::Bhlast(bhlast) => {
                 let client = new_client()?;
@@ -88,7 +132,7 @@ impl Commands {
             }
             Commands::Blob(blob) => {
                 let client = new_client()?;
-                blob.run(client)?
+                blob.run(client, progress, &mut io::stdin(), &mut io::stdout())?
             }
         }
 
@@ -96,7 +140,9 @@ impl Commands {
     }
 }
 
-fn new_client() -> Result<HTTPClient> {
+/// Creates the client, taking the rate limit from the config file when no
+/// `--limit-rate` is given.
+fn new_client(limit_rate: Option<u64>) -> Result<HTTPClient> {
     let pat = match env::var("BOUNTYHUB_TOKEN") {
         Ok(token) => {
             if !token.starts_with("bhv") {
@@ -111,7 +157,31 @@ fn new_client() -> Result<HTTPClient> {
 
     let bountyhub = env::var("BOUNTYHUB_URL").unwrap_or("https://bountyhub.org".to_string());
 
-    Ok(HTTPClient::new(&bountyhub, &pat, env!("CARGO_PKG_VERSION")))
+    let config = Config::load()?;
+    let limit_rate = match limit_rate {
+        Some(limit_rate) => Some(limit_rate),
+        None => config.limit_rate.as_deref().map(parse_size).transpose()?,
+    };
+    let mut client = HTTPClient::new(&bountyhub, &pat, env!("CARGO_PKG_VERSION"));
+    if let Some(limit_rate) = limit_rate {
+        client = client.with_rate_limit(limit_rate);
+    }
+    if let Some(cache) = download_cache(&config)? {
+        client = client.with_download_cache(cache);
+    }
+    match env::var_os("BOUNTYHUB_IDENTITY_FILE") {
+        Some(path) => Ok(client.with_identity_file(PathBuf::from(path))),
+        None => Ok(client),
+    }
+}
+
+/// Download cache in the user cache directory, sized by `cacheMaxSize`.
+fn download_cache(config: &Config) -> Result<Option<DownloadCache>> {
+    let max_size = match &config.cache_max_size {
+        Some(max_size) => parse_size(max_size)?,
+        None => download_cache::DEFAULT_MAX_SIZE,
+    };
+    Ok(config::cache_dir().map(|dir| DownloadCache::new(dir.join("downloads"), max_size)))
 }
 
 /// Job based commands
@@ -132,9 +202,10 @@ enum Job {
 }
 
 impl Job {
-    fn run<C>(self, client: C) -> Result<()>
+    fn run<C, W>(self, client: C, progress: &Progress, stdout: &mut W) -> Result<()>
     where
         C: Client,
+        W: Write,
     {
         match self {
             Job::Delete { job_id } => {
@@ -144,7 +215,7 @@ impl Job {
 
                 Ok(())
             }
-            Job::Artifact(artifact) => artifact.run(client),
+            Job::Artifact(artifact) => artifact.run(client, progress, stdout),
         }
     }
 }
@@ -152,9 +223,79 @@ impl Job {
 #[derive(Subcommand, Debug, Clone)]
 pub enum JobArtifact {
     /// Download an artifact uploaded by a job
+    ///
+    /// With --latest, the artifact is taken from the most recent job of the
+    /// scan that uploaded it, instead of the job given by --job-id.
     #[command(name = "download")]
-    #[command(about = "Download a file from the internet")]
     Download {
+        #[arg(short, long, env = "BOUNTYHUB_JOB_ID")]
+        #[arg(required_unless_present = "latest")]
+        job_id: Option<Uuid>,
+
+        /// download from the most recent job of the scan instead of --job-id
+        #[arg(long, requires_all = ["workflow_id", "scan_name"])]
+        latest: bool,
+
+        /// workflow of the scan, used with --latest
+        #[arg(short, long, env = "BOUNTYHUB_WORKFLOW_ID")]
+        workflow_id: Option<Uuid>,
+
+        /// scan whose jobs are searched, used with --latest
+        #[arg(short, long, env = "BOUNTYHUB_SCAN_NAME")]
+        scan_name: Option<String>,
+
+        /// only consider jobs in this state, used with --latest
+        #[arg(long, value_enum, requires = "latest")]
+        state: Option<JobState>,
+
+        #[arg(short, long, env = "BOUNTYHUB_JOB_ARTIFACT_NAME")]
+        #[arg(required = true)]
+        artifact_name: String,
+
+        /// output is the file or directory to write to, or '-' for stdout
+        #[arg(short, long, env = "BOUNTYHUB_OUTPUT")]
+        #[arg(value_hint = ValueHint::DirPath)]
+        output: Option<String>,
+
+        /// unpack a .zip, .tar, .tar.gz, .tgz or .tar.zst artifact into the output directory
+        #[arg(long)]
+        extract: bool,
+
+        /// stop extracting once the unpacked files exceed this many bytes, with an optional
+        /// K, M or G suffix
+        #[arg(long, default_value = "8G", value_parser = parse_size, requires = "extract")]
+        max_size: u64,
+    },
+
+    /// Compare an artifact between two jobs
+    ///
+    /// Prints a unified diff of the lines, or for JSON lines artifacts the
+    /// records removed and added, regardless of their order.
+    #[command(name = "diff")]
+    Diff {
+        /// job with the old version of the artifact
+        #[arg(long)]
+        from_job: Uuid,
+
+        /// job with the new version of the artifact
+        #[arg(long)]
+        to_job: Uuid,
+
+        #[arg(short, long, env = "BOUNTYHUB_JOB_ARTIFACT_NAME")]
+        #[arg(required = true)]
+        artifact_name: String,
+
+        /// how the two versions are compared
+        #[arg(long, value_enum, default_value_t = DiffFormat::Auto)]
+        format: DiffFormat,
+    },
+
+    /// List the files in a zip artifact, or extract one of them
+    ///
+    /// Only the end of the archive and the extracted file are downloaded,
+    /// using range requests, so large archives are not downloaded in full.
+    #[command(name = "ls-archive")]
+    LsArchive {
         #[arg(short, long, env = "BOUNTYHUB_JOB_ID")]
         #[arg(required = true)]
         job_id: Uuid,
@@ -163,11 +304,32 @@ pub enum JobArtifact {
         #[arg(required = true)]
         artifact_name: String,
 
-        #[arg(short, long, env = "BOUNTYHUB_OUTPUT")]
+        /// file in the archive to extract instead of listing the archive
+        #[arg(long)]
+        extract: Option<String>,
+
+        /// output is the file or directory to extract to, or '-' for stdout
+        #[arg(short, long, requires = "extract")]
         #[arg(value_hint = ValueHint::DirPath)]
         output: Option<String>,
     },
 
+    /// Print a time-limited URL the artifact can be downloaded from without a token
+    #[command(name = "url")]
+    Url {
+        #[arg(short, long, env = "BOUNTYHUB_JOB_ID")]
+        #[arg(required = true)]
+        job_id: Uuid,
+
+        #[arg(short, long, env = "BOUNTYHUB_JOB_ARTIFACT_NAME")]
+        #[arg(required = true)]
+        artifact_name: String,
+
+        /// how long the URL stays valid, in seconds or with an s, m, h or d suffix
+        #[arg(long, value_parser = parse_duration)]
+        expires_in: Option<u64>,
+    },
+
     /// Delete job artifact
     #[command(name = "delete")]
     #[command(about = "Delete job artifact")]
@@ -182,17 +344,114 @@ pub enum JobArtifact {
     },
 }
 
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobState {
+    Succeeded,
+    Failed,
+    Cancelled,
+}
+
+impl JobState {
+    fn as_str(&self) -> &'static str {
+        match self {
+            JobState::Succeeded => "succeeded",
+            JobState::Failed => "failed",
+            JobState::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// Finds the most recent job of the scan that uploaded the artifact.
+fn latest_job_with_artifact<C>(
+    client: &C,
+    workflow_id: Uuid,
+    scan_name: &str,
+    state: Option<JobState>,
+    artifact_name: &str,
+) -> Result<Uuid>
+where
+    C: Client,
+{
+    let jobs = client
+        .list_scan_jobs(
+            workflow_id,
+            scan_name,
+            state.map(|s| s.as_str().to_string()),
+        )
+        .map_err(|err| format!("Failed to list jobs of scan '{scan_name}': {err:?}"))?;
+
+    jobs.into_iter()
+        .filter(|job| state.is_none_or(|s| job.state == s.as_str()))
+        .filter(|job| job.artifacts.iter().any(|a| a == artifact_name))
+        .max_by(|a, b| a.created_at.cmp(&b.created_at))
+        .map(|job| job.id)
+        .ok_or_else(|| {
+            format!("No job of scan '{scan_name}' has uploaded artifact '{artifact_name}'")
+        })
+}
+
 impl JobArtifact {
-    fn run<C>(self, client: C) -> Result<()>
+    fn run<C, W>(self, client: C, progress: &Progress, stdout: &mut W) -> Result<()>
     where
         C: Client,
+        W: Write,
     {
         match self {
             JobArtifact::Download {
                 job_id,
+                latest,
+                workflow_id,
+                scan_name,
+                state,
                 artifact_name,
                 output,
+                extract,
+                max_size,
             } => {
+                let job_id = match (latest, workflow_id, scan_name, job_id) {
+                    (true, Some(workflow_id), Some(scan_name), _) => latest_job_with_artifact(
+                        &client,
+                        workflow_id,
+                        &scan_name,
+                        state,
+                        &artifact_name,
+                    )?,
+                    (false, _, _, Some(job_id)) => job_id,
+                    _ => return Err("Either --job-id or --latest has to be set".to_string()),
+                };
+
+                if extract {
+                    if output.as_deref() == Some(STDIO) {
+                        return Err("Cannot extract an artifact to stdout".to_string());
+                    }
+                    let format = extract::Format::from_name(&artifact_name)?;
+                    let root = match output {
+                        Some(output) => PathBuf::from(output),
+                        None => env::current_dir()
+                            .map_err(|err| format!("Failed to get current directory: {err:?}"))?,
+                    };
+
+                    let (freader, size) = client
+                        .download_job_artifact(job_id, &artifact_name)
+                        .map_err(|err| format!("Failed to download file: {err:?}"))?;
+                    let tracker = progress.start(&artifact_name, size);
+                    extract::extract(tracker.wrap_read(freader), format, &root, max_size)?;
+                    tracker.finish();
+                    return Ok(());
+                }
+
+                if output.as_deref() == Some(STDIO) {
+                    let (freader, size) = client
+                        .download_job_artifact(job_id, &artifact_name)
+                        .map_err(|err| format!("Failed to download file: {err:?}"))?;
+
+                    let tracker = progress.start(&artifact_name, size);
+                    io::copy(&mut tracker.wrap_read(freader), stdout)
+                        .map_err(|err| format!("failed to write to stdout: {err:?}"))?;
+                    tracker.finish();
+                    return Ok(());
+                }
+
                 let output = match output {
                     Some(output) => {
                         let output = PathBuf::from(output);
@@ -207,15 +466,117 @@ impl JobArtifact {
                         .join(&artifact_name),
                 };
 
-                let mut freader = client
+                let (freader, size) = client
                     .download_job_artifact(job_id, &artifact_name)
                     .map_err(|err| format!("Failed to download file: {err:?}"))?;
 
                 let mut fwriter = fs::File::create(output)
                     .map_err(|err| format!("Failed to create file: {err:?}"))?;
 
-                std::io::copy(&mut *freader, &mut fwriter)
+                let tracker = progress.start(&artifact_name, size);
+                std::io::copy(&mut tracker.wrap_read(freader), &mut fwriter)
+                    .map_err(|err| format!("failed to write file: {err:?}"))?;
+                tracker.finish();
+            }
+            JobArtifact::Diff {
+                from_job,
+                to_job,
+                artifact_name,
+                format,
+            } => {
+                let read = |job_id: Uuid| -> Result<String> {
+                    let (freader, size) = client
+                        .download_job_artifact(job_id, &artifact_name)
+                        .map_err(|err| {
+                            format!("Failed to download artifact of job '{job_id}': {err:?}")
+                        })?;
+                    let tracker = progress.start(&format!("{job_id}/{artifact_name}"), size);
+                    let mut content = String::new();
+                    tracker
+                        .wrap_read(freader)
+                        .read_to_string(&mut content)
+                        .map_err(|err| {
+                            format!("Failed to read artifact of job '{job_id}': {err:?}")
+                        })?;
+                    tracker.finish();
+                    Ok(content)
+                };
+
+                let out = artifact_diff::diff(
+                    &read(from_job)?,
+                    &read(to_job)?,
+                    format,
+                    &from_job.to_string(),
+                    &to_job.to_string(),
+                )?;
+                print!("{out}");
+            }
+            JobArtifact::LsArchive {
+                job_id,
+                artifact_name,
+                extract,
+                output,
+            } => {
+                let url = client
+                    .get_job_artifact_url(job_id, &artifact_name, None)
+                    .map_err(|err| format!("Failed to get artifact URL: {err:?}"))?;
+                let entries = remote_zip::list(&client, &url)
+                    .map_err(|err| format!("Failed to list '{artifact_name}': {err}"))?;
+
+                let Some(member) = extract else {
+                    for entry in entries {
+                        println!(
+                            "{}\t{}\t{}\t{}",
+                            entry.size, entry.compressed_size, entry.modified, entry.name
+                        );
+                    }
+                    return Ok(());
+                };
+
+                let entry = entries
+                    .iter()
+                    .find(|entry| entry.name == member && !entry.is_dir())
+                    .ok_or_else(|| format!("'{member}' is not a file in '{artifact_name}'"))?;
+                let freader = remote_zip::open(&client, &url, entry)?;
+                let tracker = progress.start(&member, Some(entry.size));
+
+                if output.as_deref() == Some(STDIO) {
+                    io::copy(&mut tracker.wrap_read(freader), stdout)
+                        .map_err(|err| format!("failed to write to stdout: {err:?}"))?;
+                    tracker.finish();
+                    return Ok(());
+                }
+
+                let file_name = member.rsplit('/').next().unwrap_or(&member);
+                let output = match output {
+                    Some(output) => {
+                        let output = PathBuf::from(output);
+                        if output.is_dir() {
+                            output.join(file_name)
+                        } else {
+                            output
+                        }
+                    }
+                    None => env::current_dir()
+                        .map_err(|err| format!("Failed to get current directory: {err:?}"))?
+                        .join(file_name),
+                };
+
+                let mut fwriter = fs::File::create(output)
+                    .map_err(|err| format!("Failed to create file: {err:?}"))?;
+                io::copy(&mut tracker.wrap_read(freader), &mut fwriter)
                     .map_err(|err| format!("failed to write file: {err:?}"))?;
+                tracker.finish();
+            }
+            JobArtifact::Url {
+                job_id,
+                artifact_name,
+                expires_in,
+            } => {
+                let url = client
+                    .get_job_artifact_url(job_id, &artifact_name, expires_in)
+                    .map_err(|err| format!("Failed to get artifact URL: {err:?}"))?;
+                println!("{url}");
             }
             JobArtifact::Delete {
                 job_id,
@@ -258,6 +619,45 @@ fn split_input(input: &str) -> Result<(&str, &str)> {
     ))
 }
 
+/// Builds scan inputs from the `key=value` pairs of the input flags.
+fn build_inputs(
+    input_string: Option<Vec<String>>,
+    input_bool: Option<Vec<String>>,
+) -> Result<Option<BTreeMap<String, Value>>> {
+    let inputs = if input_string.is_some() || input_bool.is_some() {
+        let mut m = BTreeMap::new();
+
+        if let Some(input_string) = input_string {
+            for v in input_string {
+                let (k, v) = split_input(v.as_str())?;
+                if !validation::valid_workflow_var_key(k) {
+                    return Err(format!("Key '{k}' is in invalid format"));
+                }
+                m.insert(k.to_string(), Value::String(v.to_string()));
+            }
+        }
+
+        if let Some(input_bool) = input_bool {
+            for v in input_bool {
+                let (k, v) = split_input(v.as_str())?;
+                if !validation::valid_workflow_var_key(k) {
+                    return Err(format!("Key '{k}' is in invalid format"));
+                }
+                let b = v
+                    .parse::<bool>()
+                    .map_err(|_| format!("Value '{v}' is not a valid boolean"))?;
+                m.insert(k.to_string(), Value::Bool(b));
+            }
+        }
+
+        Some(m)
+    } else {
+        None
+    };
+
+    Ok(inputs)
+}
+
 impl Scan {
     fn run<C>(self, client: C) -> Result<()>
     where
@@ -274,36 +674,7 @@ impl Scan {
                     return Err(format!("Invalid scan name: '{scan_name}'"));
                 }
 
-                let inputs = if input_string.is_some() || input_bool.is_some() {
-                    let mut m = BTreeMap::new();
-
-                    if let Some(input_string) = input_string {
-                        for v in input_string {
-                            let (k, v) = split_input(v.as_str())?;
-                            if !validation::valid_workflow_var_key(k) {
-                                return Err(format!("Key '{k}' is in invalid format"));
-                            }
-                            m.insert(k.to_string(), Value::String(v.to_string()));
-                        }
-                    }
-
-                    if let Some(input_bool) = input_bool {
-                        for v in input_bool {
-                            let (k, v) = split_input(v.as_str())?;
-                            if !validation::valid_workflow_var_key(k) {
-                                return Err(format!("Key '{k}' is in invalid format"));
-                            }
-                            let b = v
-                                .parse::<bool>()
-                                .map_err(|_| format!("Value '{v}' is not a valid boolean"))?;
-                            m.insert(k.to_string(), Value::Bool(b));
-                        }
-                    }
-
-                    Some(m)
-                } else {
-                    None
-                };
+                let inputs = build_inputs(input_string, input_bool)?;
 
                 client
                     .dispatch_scan(workflow_id, scan_name, inputs)
@@ -316,259 +687,2880 @@ impl Scan {
 }
 
 #[derive(Subcommand, Debug, Clone)]
-enum Blob {
-    /// Download a file from bountyhub.org blob storage
-    Download {
-        #[arg(short, long, required = true)]
-        src: String,
-        #[arg(short, long, env = "BOUNTYHUB_OUTPUT")]
-        #[arg(value_hint = ValueHint::DirPath)]
-        dst: Option<String>,
-    },
-    /// Upload a file to bountyhub.org blob storage
-    Upload {
-        /// src is the source file on the local filesystem
+enum Workflow {
+    /// Push a local workflow file as a new revision of the workflow
+    ///
+    /// Prints the ID of the new revision. With --create, a new workflow is
+    /// created instead and its ID is printed.
+    Push {
+        #[arg(short, long, env = "BOUNTYHUB_WORKFLOW_ID")]
+        #[arg(required_unless_present = "create")]
+        workflow_id: Option<Uuid>,
+
+        /// file is the workflow definition on the local filesystem
         #[arg(short, long, required = true)]
-        #[arg(value_hint = ValueHint::DirPath)]
-        src: String,
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: String,
 
-        /// dst is the destination path on bountyhub.org blobs
-        #[arg(long, required = true)]
-        dst: String,
+        /// create a new workflow instead of adding a revision
+        #[arg(long, requires = "name")]
+        create: bool,
+
+        /// name of the workflow created with --create
+        #[arg(short, long)]
+        name: Option<String>,
     },
+
+    /// Validate workflow files locally, without contacting the server
+    Validate(WorkflowValidate),
+
+    /// Workflow revision related commands
+    #[command(subcommand)]
+    Revisions(WorkflowRevisions),
+
+    /// Print the job dependency graph of a workflow
+    Graph(WorkflowGraph),
+
+    /// Write a starter workflow from a built-in template
+    Init(WorkflowInit),
+
+    /// Run the jobs of a scan on the local machine
+    RunLocal(WorkflowRunLocal),
+
+    /// Workflow variable related commands
+    #[command(subcommand)]
+    Vars(WorkflowVars),
+
+    /// Workflow secret related commands
+    #[command(subcommand)]
+    Secrets(WorkflowSecrets),
 }
 
-impl Blob {
+impl Workflow {
     fn run<C>(self, client: C) -> Result<()>
     where
         C: Client,
     {
         match self {
-            Blob::Download {
-                src: path,
-                dst: output,
+            Workflow::Push {
+                workflow_id,
+                file,
+                create,
+                name,
             } => {
-                let output = match output {
-                    Some(output) => {
-                        let output = PathBuf::from(output);
-                        if output.is_dir() {
-                            output.join(&path)
-                        } else {
-                            output
-                        }
-                    }
-                    None => env::current_dir()
-                        .map_err(|err| format!("Failed to get current directory: {err:?}"))?
-                        .join(Path::new(&path).file_name().unwrap_or_default()),
-                };
+                let source = fs::read_to_string(&file)
+                    .map_err(|err| format!("Failed to read file '{file}': {err:?}"))?;
 
-                let mut freader = client
-                    .download_blob_file(&path)
-                    .map_err(|err| format!("Failed to download file: {err:?}"))?;
+                if create {
+                    let name = name.ok_or("Workflow name is required with --create")?;
+                    let resp = client
+                        .create_workflow(name, source)
+                        .map_err(|err| format!("Failed to create workflow: {err:?}"))?;
 
-                let mut fwriter = fs::File::create(output)
-                    .map_err(|err| format!("Failed to create output file: {err:?}"))?;
+                    println!("{}", resp.workflow_id);
+                } else {
+                    let workflow_id = workflow_id.ok_or("Workflow ID is required")?;
+                    let resp = client
+                        .create_workflow_revision(workflow_id, source)
+                        .map_err(|err| format!("Failed to push workflow revision: {err:?}"))?;
 
-                std::io::copy(&mut *freader, &mut fwriter)
-                    .map_err(|err| format!("Failed to write to output: {err:?}"))?;
-                Ok(())
-            }
-            Blob::Upload { src, dst } => {
-                let freader = fs::File::open(&src)
-                    .map_err(|err| format!("Failed to open file '{src}': {err:?}"))?;
+                    println!("{}", resp.revision_id);
+                }
 
-                client
-                    .upload_blob_file(freader, dst.as_str())
-                    .map_err(|err| format!("Failed to upload blob file: {err:?}"))?;
                 Ok(())
             }
+            Workflow::Validate(validate) => validate.run(),
+            Workflow::Revisions(revisions) => revisions.run(client),
+            Workflow::Graph(graph) => graph.run(|| Ok(client)),
+            Workflow::Init(init) => init.run(),
+            Workflow::RunLocal(run_local) => run_local.run(),
+            Workflow::Vars(vars) => vars.run(client),
+            Workflow::Secrets(secrets) => secrets.run(client, &mut io::stdin().lock()),
         }
     }
 }
 
 #[derive(Subcommand, Debug, Clone)]
-enum Runner {
-    /// Runner registration commands
-    #[command(subcommand)]
-    Registration(RunnerRegistration),
+enum WorkflowRevisions {
+    /// List revisions of the workflow
+    List {
+        #[arg(short, long, env = "BOUNTYHUB_WORKFLOW_ID", required = true)]
+        workflow_id: Uuid,
+    },
+
+    /// Show what changed between two revisions of the workflow
+    Diff {
+        #[arg(short, long, env = "BOUNTYHUB_WORKFLOW_ID", required = true)]
+        workflow_id: Uuid,
+
+        /// from is the older revision
+        from: Uuid,
+
+        /// to is the newer revision
+        to: Uuid,
+    },
 }
 
-impl Runner {
+impl WorkflowRevisions {
     fn run<C>(self, client: C) -> Result<()>
     where
         C: Client,
     {
         match self {
-            Runner::Registration(registration) => registration.run(client)?,
+            WorkflowRevisions::List { workflow_id } => {
+                let revisions = client
+                    .list_workflow_revisions(workflow_id)
+                    .map_err(|err| format!("Failed to list workflow revisions: {err:?}"))?;
+
+                for revision in revisions {
+                    println!("{}\t{}", revision.id, revision.created_at);
+                }
+            }
+            WorkflowRevisions::Diff {
+                workflow_id,
+                from,
+                to,
+            } => {
+                let from_source = client
+                    .get_workflow_revision_source(workflow_id, from)
+                    .map_err(|err| format!("Failed to get revision '{from}': {err:?}"))?;
+                let to_source = client
+                    .get_workflow_revision_source(workflow_id, to)
+                    .map_err(|err| format!("Failed to get revision '{to}': {err:?}"))?;
+
+                let (diff, warning) = revision_diff(&from_source, &to_source, from, to);
+                if let Some(warning) = warning {
+                    eprintln!("Warning: {warning}");
+                }
+                print!("{diff}");
+            }
         }
 
         Ok(())
     }
 }
 
-#[derive(Subcommand, Debug, Clone)]
-enum RunnerRegistration {
-    /// Get newly created runner registration token
-    #[command(name = "token")]
-    Token,
+/// Summary of structural changes followed by a unified diff of the sources,
+/// along with a warning when the summary is skipped.
+fn revision_diff(
+    from_source: &str,
+    to_source: &str,
+    from: Uuid,
+    to: Uuid,
+) -> (String, Option<String>) {
+    let (from_workflow, from_diagnostics) = workflow::Workflow::load(from_source);
+    let (to_workflow, to_diagnostics) = workflow::Workflow::load(to_source);
 
-    /// Get runner registration command with newly created token
-    #[command(name = "command")]
-    Command,
+    let mut out = String::new();
+    // The summary of a revision that does not load would be meaningless
+    let invalid = [(from, &from_diagnostics), (to, &to_diagnostics)]
+        .into_iter()
+        .filter(|(_, diagnostics)| !diagnostics.is_empty())
+        .map(|(revision, _)| revision.to_string())
+        .collect::<Vec<_>>();
+    let warning = if invalid.is_empty() {
+        for change in from_workflow.structural_changes(&to_workflow) {
+            out.push_str(&change);
+            out.push('\n');
+        }
+        None
+    } else {
+        Some(format!(
+            "revision {} has problems, skipping the summary of changes",
+            invalid.join(" and ")
+        ))
+    };
+    if !out.is_empty() {
+        out.push('\n');
+    }
+
+    let diff = similar::TextDiff::from_lines(from_source, to_source);
+    out.push_str(
+        &diff
+            .unified_diff()
+            .header(&from.to_string(), &to.to_string())
+            .to_string(),
+    );
+    (out, warning)
 }
 
-impl RunnerRegistration {
-    fn run<C>(self, client: C) -> Result<()>
-    where
-        C: Client,
-    {
-        match self {
-            RunnerRegistration::Token => {
-                let resp = client
-                    .create_runner_registration()
-                    .map_err(|err| format!("Failed to create runner registration: {err:?}"))?;
+#[derive(Args, Debug, Clone)]
+struct WorkflowValidate {
+    /// files are workflow definitions on the local filesystem
+    #[arg(required = true)]
+    #[arg(value_hint = ValueHint::FilePath)]
+    files: Vec<String>,
+}
 
-                print!("{}", resp.token);
-            }
-            RunnerRegistration::Command => {
-                let resp = client
-                    .create_runner_registration()
-                    .map_err(|err| format!("Failed to create runner registration: {err:?}"))?;
+impl WorkflowValidate {
+    fn run(&self) -> Result<()> {
+        let mut problems = 0;
 
-                println!(
-                    r#"runner configure --token "{}" --url "{}""#,
-                    resp.token, resp.url,
-                );
+        for file in &self.files {
+            let source = fs::read_to_string(file)
+                .map_err(|err| format!("Failed to read file '{file}': {err:?}"))?;
+
+            if let Err(diagnostics) = workflow::Workflow::parse(&source) {
+                for diagnostic in &diagnostics {
+                    eprintln!("{file}:{diagnostic}");
+                }
+                problems += diagnostics.len();
             }
         }
 
+        if problems > 0 {
+            return Err(format!("Found {problems} problem(s) in workflow files"));
+        }
+
         Ok(())
     }
 }
 
-#[cfg(test)]
-mod job_tests {
-    use super::*;
-    use crate::client::{Error as ClientError, MockClient};
-    use mockall::predicate::*;
+/// Parses a workflow, turning every problem found into a single error.
+fn parse_workflow(name: &str, source: &str) -> Result<workflow::Workflow> {
+    workflow::Workflow::parse(source).map_err(|diagnostics| {
+        let lines = diagnostics
+            .iter()
+            .map(|diagnostic| format!("{name}:{diagnostic}"))
+            .collect::<Vec<_>>();
+        format!("Invalid workflow:\n{}", lines.join("\n"))
+    })
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+#[derive(Args, Debug, Clone)]
+struct WorkflowGraph {
+    /// file is the workflow definition on the local filesystem
+    #[arg(short, long, conflicts_with = "revision_id")]
+    #[arg(value_hint = ValueHint::FilePath)]
+    file: Option<String>,
+
+    /// workflow to fetch the revision from, when --file is not set
+    #[arg(short, long, env = "BOUNTYHUB_WORKFLOW_ID")]
+    workflow_id: Option<Uuid>,
+
+    /// revision to render, defaults to the latest revision
+    #[arg(short, long)]
+    revision_id: Option<Uuid>,
+
+    #[arg(long, value_enum, default_value_t = GraphFormat::Dot)]
+    format: GraphFormat,
+}
+
+impl WorkflowGraph {
+    /// The client is only created when the workflow is fetched from the server.
+    fn run<C, F>(&self, client: F) -> Result<()>
+    where
+        C: Client,
+        F: FnOnce() -> Result<C>,
+    {
+        let (name, source) = match &self.file {
+            Some(file) => {
+                let source = fs::read_to_string(file)
+                    .map_err(|err| format!("Failed to read file '{file}': {err:?}"))?;
+                (file.clone(), source)
+            }
+            None => {
+                let workflow_id = self
+                    .workflow_id
+                    .ok_or("Either --file or --workflow-id is required")?;
+                let client = client()?;
+                let revision_id = match self.revision_id {
+                    Some(revision_id) => revision_id,
+                    None => {
+                        client
+                            .list_workflow_revisions(workflow_id)
+                            .map_err(|err| format!("Failed to list workflow revisions: {err:?}"))?
+                            .into_iter()
+                            .max_by(|a, b| a.created_at.cmp(&b.created_at))
+                            .ok_or("Workflow has no revisions")?
+                            .id
+                    }
+                };
+                let source = client
+                    .get_workflow_revision_source(workflow_id, revision_id)
+                    .map_err(|err| format!("Failed to get revision '{revision_id}': {err:?}"))?;
+                (revision_id.to_string(), source)
+            }
+        };
+
+        let workflow = parse_workflow(&name, &source)?;
+        match self.format {
+            GraphFormat::Dot => print!("{}", workflow.to_dot()),
+            GraphFormat::Mermaid => print!("{}", workflow.to_mermaid()),
+        }
+
+        Ok(())
+    }
+}
+
+fn template_parser() -> PossibleValuesParser {
+    PossibleValuesParser::new(
+        template::TEMPLATES
+            .iter()
+            .map(|t| PossibleValue::new(t.name).help(t.description)),
+    )
+}
+
+#[derive(Args, Debug, Clone)]
+struct WorkflowInit {
+    #[arg(short, long, required = true, value_parser = template_parser())]
+    template: String,
+
+    /// var sets a template variable in the key=value format instead of prompting for it
+    #[arg(long)]
+    var: Option<Vec<String>>,
+
+    /// output is the path of the created workflow file
+    #[arg(short, long, default_value = "workflow.yaml")]
+    #[arg(value_hint = ValueHint::FilePath)]
+    output: String,
+
+    /// overwrite the output file if it already exists
+    #[arg(long)]
+    force: bool,
+
+    /// never prompt for template variables, using the defaults of the ones not set with --var
+    #[arg(long)]
+    no_input: bool,
+}
+
+impl WorkflowInit {
+    fn run(&self) -> Result<()> {
+        let template = template::get(&self.template)
+            .ok_or_else(|| format!("Unknown template '{}'", self.template))?;
+
+        let mut vars = BTreeMap::new();
+        for v in self.var.iter().flatten() {
+            let (k, v) = split_input(v)?;
+            vars.insert(k.to_string(), v.to_string());
+        }
+
+        if !self.no_input && io::stdin().is_terminal() {
+            prompt_template_vars(
+                template,
+                &mut vars,
+                &mut io::stdin().lock(),
+                &mut io::stderr(),
+            )?;
+        }
+
+        let source = template.render(&vars)?;
+
+        let output = Path::new(&self.output);
+        if output.exists() && !self.force {
+            return Err(format!(
+                "File '{}' already exists, use --force to overwrite it",
+                self.output
+            ));
+        }
+        fs::write(output, source)
+            .map_err(|err| format!("Failed to write file '{}': {err:?}", self.output))?;
+
+        Ok(())
+    }
+}
+
+/// Asks for every template variable that is not set yet, keeping the default on empty input.
+fn prompt_template_vars<R, W>(
+    template: &template::Template,
+    vars: &mut BTreeMap<String, String>,
+    input: &mut R,
+    output: &mut W,
+) -> Result<()>
+where
+    R: BufRead,
+    W: Write,
+{
+    for var in template.vars {
+        if vars.contains_key(var.name) {
+            continue;
+        }
+
+        write!(output, "{} [{}]: ", var.description, var.default)
+            .and_then(|_| output.flush())
+            .map_err(|err| format!("Failed to write prompt: {err:?}"))?;
+
+        let mut line = String::new();
+        input
+            .read_line(&mut line)
+            .map_err(|err| format!("Failed to read input: {err:?}"))?;
+
+        let value = line.trim();
+        if !value.is_empty() {
+            vars.insert(var.name.to_string(), value.to_string());
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Args, Debug, Clone)]
+struct WorkflowRunLocal {
+    /// file is the workflow definition on the local filesystem
+    #[arg(short, long, required = true)]
+    #[arg(value_hint = ValueHint::FilePath)]
+    file: String,
+
+    #[arg(short, long, env = "BOUNTYHUB_SCAN_NAME", required = true)]
+    scan_name: String,
+
+    #[arg(long)]
+    input_string: Option<Vec<String>>,
+
+    #[arg(long)]
+    input_bool: Option<Vec<String>>,
+
+    /// output is the directory holding job workspaces and artifacts
+    #[arg(short, long, default_value = ".bh-run")]
+    #[arg(value_hint = ValueHint::DirPath)]
+    output: String,
+}
+
+impl WorkflowRunLocal {
+    fn run(self) -> Result<()> {
+        if !validation::valid_scan_name(&self.scan_name) {
+            return Err(format!("Invalid scan name: '{}'", self.scan_name));
+        }
+
+        let source = fs::read_to_string(&self.file)
+            .map_err(|err| format!("Failed to read file '{}': {err:?}", self.file))?;
+        let workflow = parse_workflow(&self.file, &source)?;
+        let inputs = build_inputs(self.input_string, self.input_bool)?;
+
+        local_run::run(&workflow, &self.scan_name, inputs, Path::new(&self.output))
+    }
+}
+
+fn validate_var_key(key: &str) -> Result<()> {
+    if !validation::valid_workflow_var_key(key) {
+        return Err(format!("Key '{key}' is in invalid format"));
+    }
+    Ok(())
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum WorkflowVars {
+    /// Set a workflow variable, creating it if it does not exist
+    Set {
+        #[arg(short, long, env = "BOUNTYHUB_WORKFLOW_ID", required = true)]
+        workflow_id: Uuid,
+
+        #[arg(short, long, required = true)]
+        key: String,
+
+        #[arg(long, required = true)]
+        value: String,
+    },
+
+    /// List workflow variables and their values
+    List {
+        #[arg(short, long, env = "BOUNTYHUB_WORKFLOW_ID", required = true)]
+        workflow_id: Uuid,
+    },
+
+    /// Delete a workflow variable
+    Delete {
+        #[arg(short, long, env = "BOUNTYHUB_WORKFLOW_ID", required = true)]
+        workflow_id: Uuid,
+
+        #[arg(short, long, required = true)]
+        key: String,
+    },
+}
+
+impl WorkflowVars {
+    fn run<C>(self, client: C) -> Result<()>
+    where
+        C: Client,
+    {
+        match self {
+            WorkflowVars::Set {
+                workflow_id,
+                key,
+                value,
+            } => {
+                validate_var_key(&key)?;
+                client
+                    .set_workflow_var(workflow_id, &key, value)
+                    .map_err(|err| format!("Failed to set workflow variable: {err:?}"))?;
+            }
+            WorkflowVars::List { workflow_id } => {
+                let vars = client
+                    .list_workflow_vars(workflow_id)
+                    .map_err(|err| format!("Failed to list workflow variables: {err:?}"))?;
+
+                for var in vars {
+                    println!("{}={}", var.key, var.value);
+                }
+            }
+            WorkflowVars::Delete { workflow_id, key } => {
+                validate_var_key(&key)?;
+                client
+                    .delete_workflow_var(workflow_id, &key)
+                    .map_err(|err| format!("Failed to delete workflow variable: {err:?}"))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum WorkflowSecrets {
+    /// Set a workflow secret, creating it if it does not exist
+    ///
+    /// The value is read from --file, or from stdin when --file is not set,
+    /// so it never shows up in the shell history.
+    Set {
+        #[arg(short, long, env = "BOUNTYHUB_WORKFLOW_ID", required = true)]
+        workflow_id: Uuid,
+
+        #[arg(short, long, required = true)]
+        key: String,
+
+        /// file holds the secret value
+        #[arg(short, long)]
+        #[arg(value_hint = ValueHint::FilePath)]
+        file: Option<String>,
+    },
+
+    /// List workflow secret keys, without their values
+    List {
+        #[arg(short, long, env = "BOUNTYHUB_WORKFLOW_ID", required = true)]
+        workflow_id: Uuid,
+    },
+
+    /// Delete a workflow secret
+    Delete {
+        #[arg(short, long, env = "BOUNTYHUB_WORKFLOW_ID", required = true)]
+        workflow_id: Uuid,
+
+        #[arg(short, long, required = true)]
+        key: String,
+    },
+}
+
+impl WorkflowSecrets {
+    fn run<C, R>(self, client: C, stdin: &mut R) -> Result<()>
+    where
+        C: Client,
+        R: Read,
+    {
+        match self {
+            WorkflowSecrets::Set {
+                workflow_id,
+                key,
+                file,
+            } => {
+                validate_var_key(&key)?;
+
+                let mut value = String::new();
+                match file {
+                    Some(file) => {
+                        value = fs::read_to_string(&file)
+                            .map_err(|err| format!("Failed to read file '{file}': {err:?}"))?;
+                    }
+                    None => {
+                        stdin
+                            .read_to_string(&mut value)
+                            .map_err(|err| format!("Failed to read secret from stdin: {err:?}"))?;
+                    }
+                }
+
+                // Drop the newline added by echo or the editor, but keep any other whitespace
+                if value.ends_with('\n') {
+                    value.pop();
+                    if value.ends_with('\r') {
+                        value.pop();
+                    }
+                }
+                if value.is_empty() {
+                    return Err("Secret value is empty".to_string());
+                }
+
+                client
+                    .set_workflow_secret(workflow_id, &key, value)
+                    .map_err(|err| format!("Failed to set workflow secret: {err:?}"))?;
+            }
+            WorkflowSecrets::List { workflow_id } => {
+                let secrets = client
+                    .list_workflow_secrets(workflow_id)
+                    .map_err(|err| format!("Failed to list workflow secrets: {err:?}"))?;
+
+                for secret in secrets {
+                    println!("{}\t{}", secret.key, secret.updated_at);
+                }
+            }
+            WorkflowSecrets::Delete { workflow_id, key } => {
+                validate_var_key(&key)?;
+                client
+                    .delete_workflow_secret(workflow_id, &key)
+                    .map_err(|err| format!("Failed to delete workflow secret: {err:?}"))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Blob {
+    /// Download a file from bountyhub.org blob storage
+    ///
+    /// Use '-' as dst to write the blob to stdout. With --recursive, every
+    /// blob under the src prefix is downloaded into the dst directory,
+    /// keeping the directory structure.
+    Download {
+        #[arg(short, long, required = true)]
+        src: String,
+        #[arg(short, long, env = "BOUNTYHUB_OUTPUT")]
+        #[arg(value_hint = ValueHint::DirPath)]
+        dst: Option<String>,
+
+        /// download every blob under the src prefix
+        #[arg(short, long)]
+        recursive: bool,
+
+        /// unpack a .zip, .tar, .tar.gz, .tgz or .tar.zst blob into the dst directory
+        #[arg(long, conflicts_with = "recursive")]
+        extract: bool,
+
+        /// stop extracting once the unpacked files exceed this many bytes, with an optional
+        /// K, M or G suffix
+        #[arg(long, default_value = "8G", value_parser = parse_size, requires = "extract")]
+        max_size: u64,
+
+        #[command(flatten)]
+        transfer: TransferOptions,
+    },
+    /// Upload a file to bountyhub.org blob storage
+    ///
+    /// When src is a directory, every file in it is uploaded under the dst
+    /// prefix, keeping the directory structure.
+    Upload {
+        /// src is the source file or directory on the local filesystem, or '-' for stdin
+        #[arg(short, long, required = true)]
+        #[arg(value_hint = ValueHint::AnyPath)]
+        src: String,
+
+        /// dst is the destination path on bountyhub.org blobs
+        #[arg(long, required = true)]
+        dst: String,
+
+        /// compress the content while uploading, downloads decompress it automatically
+        #[arg(long, value_enum)]
+        compress: Option<ContentEncoding>,
+
+        /// encrypt the content to the age identity in BOUNTYHUB_IDENTITY_FILE,
+        /// downloads decrypt it automatically
+        #[arg(long)]
+        encrypt: bool,
+
+        #[command(flatten)]
+        transfer: TransferOptions,
+    },
+    /// List blobs in bountyhub.org blob storage
+    ///
+    /// Prints the size, modification time, content hash and path of each blob.
+    Ls {
+        /// prefix limits the listing to blobs whose path starts with it
+        prefix: Option<String>,
+
+        /// list blobs in nested directories as well
+        #[arg(short, long)]
+        recursive: bool,
+    },
+    /// Show the metadata of a blob without downloading it
+    ///
+    /// Prints one tab separated key and value per line. Fails when the blob
+    /// does not exist.
+    Stat {
+        /// path of the blob
+        path: String,
+    },
+    /// Print a time-limited URL the blob can be downloaded from without a token
+    ///
+    /// The URL serves the blob as stored, so compressed or encrypted blobs
+    /// have to be decoded by whoever downloads them.
+    Url {
+        /// path of the blob
+        path: String,
+
+        /// how long the URL stays valid, in seconds or with an s, m, h or d suffix
+        #[arg(long, value_parser = parse_duration)]
+        expires_in: Option<u64>,
+    },
+    /// Delete a blob, or every blob under a prefix with --recursive
+    Rm {
+        /// path of the blob, or the prefix to delete with --recursive
+        path: String,
+
+        /// delete every blob whose path starts with the given prefix
+        #[arg(short, long)]
+        recursive: bool,
+
+        /// print what would be deleted without deleting anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Move a blob to a new path
+    Mv {
+        src: String,
+        dst: String,
+
+        /// print what would be moved without moving anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Synchronize a local directory with a blob prefix
+    ///
+    /// Uploads local files that are missing or changed under the remote
+    /// prefix, comparing sizes and SHA-256 hashes. With --download, remote
+    /// blobs are downloaded into the local directory instead.
+    Sync {
+        /// local is the directory on the local filesystem
+        #[arg(value_hint = ValueHint::DirPath)]
+        local: String,
+
+        /// remote is the blob prefix
+        remote: String,
+
+        /// download from the remote prefix into the local directory
+        #[arg(long)]
+        download: bool,
+
+        /// delete files on the target that do not exist on the source
+        #[arg(long)]
+        delete: bool,
+
+        /// print what would be transferred or deleted without doing it
+        #[arg(long)]
+        dry_run: bool,
+
+        /// compress uploaded content, downloads decompress it automatically
+        #[arg(long, value_enum, conflicts_with = "download")]
+        compress: Option<ContentEncoding>,
+
+        /// encrypt uploaded content to the age identity in BOUNTYHUB_IDENTITY_FILE,
+        /// downloads decrypt it automatically
+        #[arg(long, conflicts_with = "download")]
+        encrypt: bool,
+
+        #[command(flatten)]
+        transfer: TransferOptions,
+    },
+    /// Copy a blob to a new path
+    Cp {
+        src: String,
+        dst: String,
+
+        /// print what would be copied without copying anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Options for transfers of whole directories.
+#[derive(Args, Debug, Clone)]
+struct TransferOptions {
+    /// transfer only files matching the glob, relative to the directory
+    #[arg(long)]
+    include: Vec<String>,
+
+    /// skip files matching the glob, relative to the directory
+    #[arg(long)]
+    exclude: Vec<String>,
+
+    /// maximum number of files, or parts of a large file, transferred at the same time
+    #[arg(long, default_value_t = 4)]
+    concurrency: usize,
+}
+
+impl TransferOptions {
+    fn filter(&self) -> Result<transfer::Filter> {
+        transfer::Filter::new(&self.include, &self.exclude)
+    }
+}
+
+fn download_blob_to<C>(client: &C, path: &str, output: &Path, progress: &Progress) -> Result<()>
+where
+    C: Client,
+{
+    let (freader, size) = client
+        .download_blob_file(path)
+        .map_err(|err| format!("Failed to download file '{path}': {err:?}"))?;
+
+    let mut fwriter = fs::File::create(output).map_err(|err| {
+        format!(
+            "Failed to create output file '{}': {err:?}",
+            output.display()
+        )
+    })?;
+
+    let tracker = progress.start(path, size);
+    std::io::copy(&mut tracker.wrap_read(freader), &mut fwriter)
+        .map_err(|err| format!("Failed to write to output: {err:?}"))?;
+    tracker.finish();
+    Ok(())
+}
+
+/// Reports whether the local file differs from the content the blob was uploaded from.
+fn blob_differs(path: &Path, blob: &BlobInfo) -> Result<bool> {
+    match &blob.plaintext_hash {
+        // The size and hash of the blob are the ones of the encoded content
+        Some(hash) => transfer::file_differs(path, None, hash),
+        None => transfer::file_differs(path, Some(blob.size), &blob.hash),
+    }
+}
+
+/// Uploads a local file, sending its size along with the content.
+///
+/// Compressed or encrypted content is written to a [`transfer::Spool`] first,
+/// so that its size is known as well, and the hash of the file is recorded
+/// along with it. The file is read once, hashed while it is encoded.
+fn upload_file<C>(
+    client: &C,
+    path: &Path,
+    dst: &str,
+    connections: &transfer::Connections,
+    options: UploadOptions,
+    progress: &Progress,
+) -> Result<()>
+where
+    C: Client + Sync,
+{
+    validate_blob_path(dst)?;
+    let mut file = fs::File::open(path)
+        .map_err(|err| format!("Failed to open file '{}': {err:?}", path.display()))?;
+    if options == UploadOptions::default() {
+        return upload_content(client, &file, dst, connections, options, None, progress);
+    }
+
+    let (spool, plaintext_hash) = spool_encoded(client, &mut file, options)
+        .map_err(|err| format!("Failed to encode '{}': {err}", path.display()))?;
+    upload_content(
+        client,
+        spool.file(),
+        dst,
+        connections,
+        options,
+        Some(plaintext_hash),
+        progress,
+    )
+}
+
+/// Encodes the content into a [`transfer::Spool`], so that its size is known,
+/// returning it along with the hash of the content before it was encoded.
+///
+/// The content is encoded on the way, so only the encoded content reaches the disk.
+fn spool_encoded<C, R>(
+    client: &C,
+    reader: &mut R,
+    options: UploadOptions,
+) -> Result<(transfer::Spool, String)>
+where
+    C: Client,
+    R: Read + Send,
+{
+    // Encoders need an owned reader, so the content is fed to them through a pipe
+    let (pipe, mut pipe_writer) =
+        io::pipe().map_err(|err| format!("Failed to create pipe: {err:?}"))?;
+    thread::scope(|scope| {
+        let feeder = scope.spawn(move || {
+            let mut hashing = transfer::Hashing::new(reader);
+            io::copy(&mut hashing, &mut pipe_writer).map(|_| hashing.hash())
+        });
+        let spool = encode(client, Box::new(pipe), options)
+            .and_then(|mut encoded| transfer::Spool::from_reader(&mut encoded));
+        let plaintext_hash = feeder.join().expect("content feeder panicked");
+
+        let spool = spool?;
+        let plaintext_hash =
+            plaintext_hash.map_err(|err| format!("Failed to read content: {err:?}"))?;
+        Ok((spool, plaintext_hash))
+    })
+}
+
+/// Wraps the reader so that reading from it yields the content encoded as
+/// the options ask for.
+fn encode<C>(
+    client: &C,
+    reader: Box<dyn Read + Send>,
+    options: UploadOptions,
+) -> Result<Box<dyn Read + Send>>
+where
+    C: Client,
+{
+    // Compression has to come first, ciphertext does not compress
+    let mut encoded = reader;
+    if let Some(encoding) = options.encoding {
+        encoded = encoding
+            .encoder(encoded)
+            .map_err(|err| format!("Failed to compress: {err:?}"))?;
+    }
+    if options.encrypt {
+        encoded = client
+            .encrypt_blob(encoded)
+            .map_err(|err| format!("Failed to encrypt: {err:?}"))?;
+    }
+    Ok(encoded)
+}
+
+/// Uploads the file content as it is, along with how it was encoded.
+///
+/// Files above the multipart threshold are uploaded in parallel parts. Every
+/// upload request takes one of the `connections`, so parts of files uploaded
+/// at the same time do not multiply them.
+fn upload_content<C>(
+    client: &C,
+    file: &fs::File,
+    dst: &str,
+    connections: &transfer::Connections,
+    options: UploadOptions,
+    plaintext_hash: Option<String>,
+    progress: &Progress,
+) -> Result<()>
+where
+    C: Client + Sync,
+{
+    let size = file
+        .metadata()
+        .map_err(|err| format!("Failed to stat the content of '{dst}': {err:?}"))?
+        .len();
+
+    let tracker = progress.start(dst, Some(size));
+    if size > multipart::THRESHOLD {
+        let dst = multipart::Destination {
+            path: dst,
+            options,
+            plaintext_hash: plaintext_hash.as_deref(),
+        };
+        multipart::upload(client, file, &dst, connections, &tracker)?;
+    } else {
+        let content = transfer::Section::new(file, 0, size)
+            .map_err(|err| format!("Failed to read the content of '{dst}': {err:?}"))?;
+        let _connection = connections.acquire();
+        client
+            .upload_blob_file(
+                Box::new(tracker.wrap_read(content)),
+                size,
+                dst,
+                options,
+                plaintext_hash,
+            )
+            .map_err(|err| format!("Failed to upload blob file '{dst}': {err:?}"))?;
+    }
+    tracker.finish();
+    Ok(())
+}
+
+/// Parses a duration in seconds, optionally suffixed with s, m, h or d.
+fn parse_duration(s: &str) -> Result<u64> {
+    let (value, unit) = match s.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&s[..i], c),
+        _ => (s, 's'),
+    };
+    let multiplier = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        _ => return Err(format!("Unknown duration unit '{unit}'")),
+    };
+    let value = value
+        .parse::<u64>()
+        .map_err(|err| format!("Invalid duration '{s}': {err}"))?;
+    value
+        .checked_mul(multiplier)
+        .filter(|seconds| *seconds > 0)
+        .ok_or_else(|| format!("Invalid duration '{s}'"))
+}
+
+/// Parses a number of bytes, optionally suffixed with K, M or G.
+fn parse_size(s: &str) -> Result<u64> {
+    let (value, multiplier) = match s.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => {
+            let multiplier = match c.to_ascii_uppercase() {
+                'K' => 1024,
+                'M' => 1024 * 1024,
+                'G' => 1024 * 1024 * 1024,
+                _ => return Err(format!("Unknown size unit '{c}'")),
+            };
+            (&s[..i], multiplier)
+        }
+        _ => (s, 1),
+    };
+    let value = value
+        .parse::<u64>()
+        .map_err(|err| format!("Invalid size '{s}': {err}"))?;
+    value
+        .checked_mul(multiplier)
+        .filter(|size| *size > 0)
+        .ok_or_else(|| format!("Invalid size '{s}'"))
+}
+
+fn validate_blob_path(path: &str) -> Result<()> {
+    if !validation::valid_blob_path(path) {
+        return Err(format!("Blob path '{path}' is in invalid format"));
+    }
+    Ok(())
+}
+
+/// Lists blobs under the prefix, following every page of the listing.
+fn list_all_blobs<C>(client: &C, prefix: &str, recursive: bool) -> Result<BlobPage>
+where
+    C: Client,
+{
+    let mut listing = BlobPage::default();
+    let mut cursor = None;
+
+    loop {
+        let page = client
+            .list_blobs(prefix, recursive, cursor)
+            .map_err(|err| format!("Failed to list blobs: {err:?}"))?;
+
+        listing.blobs.extend(page.blobs);
+        listing.prefixes.extend(page.prefixes);
+
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => return Ok(listing),
+        }
+    }
+}
+
+impl Blob {
+    fn run<C, R, W>(
+        self,
+        client: C,
+        progress: &Progress,
+        stdin: &mut R,
+        stdout: &mut W,
+    ) -> Result<()>
+    where
+        C: Client + Sync,
+        R: Read + Send,
+        W: Write,
+    {
+        match self {
+            Blob::Download {
+                src: path,
+                dst: output,
+                extract: true,
+                max_size,
+                ..
+            } => {
+                if output.as_deref() == Some(STDIO) {
+                    return Err("Cannot extract a blob to stdout".to_string());
+                }
+                let format = extract::Format::from_name(&path)?;
+                let root = match output {
+                    Some(output) => PathBuf::from(output),
+                    None => env::current_dir()
+                        .map_err(|err| format!("Failed to get current directory: {err:?}"))?,
+                };
+
+                let (freader, size) = client
+                    .download_blob_file(&path)
+                    .map_err(|err| format!("Failed to download file '{path}': {err:?}"))?;
+                let tracker = progress.start(&path, size);
+                extract::extract(tracker.wrap_read(freader), format, &root, max_size)?;
+                tracker.finish();
+                Ok(())
+            }
+            Blob::Download {
+                src: path,
+                dst: output,
+                recursive: true,
+                extract: false,
+                max_size: _,
+                transfer,
+            } => {
+                if output.as_deref() == Some(STDIO) {
+                    return Err("Cannot write a recursive download to stdout".to_string());
+                }
+                let prefix = transfer::blob_dir_prefix(&path);
+                let root = match output {
+                    Some(output) => PathBuf::from(output),
+                    None => env::current_dir()
+                        .map_err(|err| format!("Failed to get current directory: {err:?}"))?,
+                };
+
+                let filter = transfer.filter()?;
+                let blobs = list_all_blobs(&client, &prefix, true)?
+                    .blobs
+                    .into_iter()
+                    .filter_map(|blob| {
+                        let relative = blob.path.strip_prefix(&prefix)?.to_string();
+                        filter.matches(&relative).then_some((blob.path, relative))
+                    })
+                    .collect::<Vec<_>>();
+
+                transfer::run_parallel(blobs, transfer.concurrency, |(path, relative)| {
+                    let output = transfer::confined_output(&root, &relative)?;
+                    download_blob_to(&client, &path, &output, progress)
+                })
+            }
+            Blob::Download {
+                src: path,
+                dst: output,
+                recursive: false,
+                extract: false,
+                max_size: _,
+                transfer: _,
+            } => {
+                if output.as_deref() == Some(STDIO) {
+                    let (freader, size) = client
+                        .download_blob_file(&path)
+                        .map_err(|err| format!("Failed to download file: {err:?}"))?;
+
+                    let tracker = progress.start(&path, size);
+                    io::copy(&mut tracker.wrap_read(freader), stdout)
+                        .map_err(|err| format!("Failed to write to stdout: {err:?}"))?;
+                    tracker.finish();
+                    return Ok(());
+                }
+
+                let output = match output {
+                    Some(output) => {
+                        let output = PathBuf::from(output);
+                        if output.is_dir() {
+                            transfer::confined_output(&output, &path)?
+                        } else {
+                            output
+                        }
+                    }
+                    None => {
+                        let cwd = env::current_dir()
+                            .map_err(|err| format!("Failed to get current directory: {err:?}"))?;
+                        let name = path.rsplit('/').next().unwrap_or_default();
+                        transfer::confined_output(&cwd, name)?
+                    }
+                };
+
+                download_blob_to(&client, &path, &output, progress)
+            }
+            Blob::Upload {
+                src,
+                dst,
+                compress,
+                encrypt,
+                transfer,
+            } => {
+                let options = UploadOptions {
+                    encoding: compress,
+                    encrypt,
+                };
+                let connections = transfer::Connections::new(transfer.concurrency);
+                if Path::new(&src).is_dir() {
+                    let filter = transfer.filter()?;
+                    let files = transfer::walk_dir(Path::new(&src))?
+                        .into_iter()
+                        .filter(|(_, relative)| filter.matches(relative))
+                        .collect::<Vec<_>>();
+
+                    return transfer::run_parallel(
+                        files,
+                        transfer.concurrency,
+                        |(path, relative)| {
+                            upload_file(
+                                &client,
+                                &path,
+                                &transfer::join_blob_path(&dst, &relative),
+                                &connections,
+                                options,
+                                progress,
+                            )
+                        },
+                    );
+                }
+
+                if src == STDIO {
+                    // Presigned uploads need the size upfront, so stdin is read to the end first
+                    validate_blob_path(&dst)?;
+                    let (spool, plaintext_hash) = if options == UploadOptions::default() {
+                        (transfer::Spool::from_reader(stdin)?, None)
+                    } else {
+                        spool_encoded(&client, stdin, options)
+                            .map(|(spool, plaintext_hash)| (spool, Some(plaintext_hash)))
+                            .map_err(|err| format!("Failed to encode stdin: {err}"))?
+                    };
+                    return upload_content(
+                        &client,
+                        spool.file(),
+                        &dst,
+                        &connections,
+                        options,
+                        plaintext_hash,
+                        progress,
+                    );
+                }
+
+                upload_file(
+                    &client,
+                    Path::new(&src),
+                    &dst,
+                    &connections,
+                    options,
+                    progress,
+                )
+            }
+            Blob::Ls { prefix, recursive } => {
+                let listing = list_all_blobs(&client, prefix.as_deref().unwrap_or(""), recursive)?;
+
+                for prefix in listing.prefixes {
+                    println!("-\t-\t-\t{prefix}");
+                }
+                for blob in listing.blobs {
+                    println!(
+                        "{}\t{}\t{}\t{}",
+                        blob.size, blob.modified_at, blob.hash, blob.path
+                    );
+                }
+                Ok(())
+            }
+            Blob::Stat { path } => {
+                let metadata = match client.get_blob_metadata(&path) {
+                    Ok(metadata) => metadata,
+                    Err(Error::NotFound) => return Err(format!("Blob '{path}' does not exist")),
+                    Err(err) => {
+                        return Err(format!("Failed to get blob metadata '{path}': {err:?}"));
+                    }
+                };
+
+                println!("path\t{}", metadata.path);
+                println!("size\t{}", metadata.size);
+                println!("content_type\t{}", metadata.content_type);
+                match metadata.content_encoding {
+                    Some(encoding) => println!("content_encoding\t{encoding}"),
+                    None => println!("content_encoding\t-"),
+                }
+                println!("encrypted\t{}", metadata.encrypted);
+                println!("hash\t{}", metadata.hash);
+                println!("uploaded_at\t{}", metadata.uploaded_at);
+                println!("uploaded_by\t{}", metadata.uploaded_by);
+                Ok(())
+            }
+            Blob::Url { path, expires_in } => {
+                let url = client
+                    .get_blob_url(&path, expires_in)
+                    .map_err(|err| format!("Failed to get blob URL '{path}': {err:?}"))?;
+                println!("{url}");
+                Ok(())
+            }
+            Blob::Rm {
+                path,
+                recursive,
+                dry_run,
+            } => {
+                let paths = if recursive {
+                    // Match whole directories only, so that 'old' does not cover 'older/'
+                    let prefix = transfer::blob_dir_prefix(&path);
+                    if prefix.trim_matches('/').is_empty() {
+                        return Err("Refusing to recursively delete every blob".to_string());
+                    }
+                    let paths = list_all_blobs(&client, &prefix, true)?
+                        .blobs
+                        .into_iter()
+                        .map(|blob| blob.path)
+                        .filter(|path| path.starts_with(&prefix))
+                        .collect::<Vec<_>>();
+                    if paths.is_empty() {
+                        return Err(format!("No blobs found under '{prefix}'"));
+                    }
+                    paths
+                } else {
+                    vec![path]
+                };
+
+                for path in paths {
+                    if dry_run {
+                        println!("Would delete '{path}'");
+                        continue;
+                    }
+                    client
+                        .delete_blob(&path)
+                        .map_err(|err| format!("Failed to delete blob '{path}': {err:?}"))?;
+                }
+                Ok(())
+            }
+            Blob::Sync {
+                local,
+                remote,
+                download,
+                delete,
+                dry_run,
+                compress,
+                encrypt,
+                transfer,
+            } => {
+                let options = UploadOptions {
+                    encoding: compress,
+                    encrypt,
+                };
+                let prefix = transfer::blob_dir_prefix(&remote);
+                let filter = transfer.filter()?;
+
+                let remote_blobs = list_all_blobs(&client, &prefix, true)?
+                    .blobs
+                    .into_iter()
+                    .filter_map(|blob| {
+                        let relative = blob.path.strip_prefix(&prefix)?.to_string();
+                        filter.matches(&relative).then_some((relative, blob))
+                    })
+                    .collect::<BTreeMap<_, _>>();
+
+                let local_root = PathBuf::from(&local);
+                let local_files = if local_root.is_dir() {
+                    transfer::walk_dir(&local_root)?
+                        .into_iter()
+                        .filter(|(_, relative)| filter.matches(relative))
+                        .map(|(path, relative)| (relative, path))
+                        .collect::<BTreeMap<_, _>>()
+                } else if download && !local_root.exists() {
+                    BTreeMap::new()
+                } else {
+                    return Err(format!("'{local}' is not a directory"));
+                };
+
+                let mut changed = Vec::new();
+                let mut extra = Vec::new();
+                if download {
+                    for (relative, blob) in &remote_blobs {
+                        let differs = match local_files.get(relative) {
+                            Some(path) => blob_differs(path, blob)?,
+                            None => true,
+                        };
+                        if differs {
+                            changed.push(relative.clone());
+                        }
+                    }
+                    extra.extend(
+                        local_files
+                            .keys()
+                            .filter(|relative| !remote_blobs.contains_key(*relative))
+                            .cloned(),
+                    );
+                } else {
+                    for (relative, path) in &local_files {
+                        let differs = match remote_blobs.get(relative) {
+                            Some(blob) => blob_differs(path, blob)?,
+                            None => true,
+                        };
+                        if differs {
+                            changed.push(relative.clone());
+                        }
+                    }
+                    extra.extend(
+                        remote_blobs
+                            .keys()
+                            .filter(|relative| !local_files.contains_key(*relative))
+                            .cloned(),
+                    );
+                }
+                if !delete {
+                    extra.clear();
+                }
+
+                let (transfer_verb, delete_verb) = if dry_run {
+                    ("Would transfer", "Would delete")
+                } else {
+                    ("Transfer", "Delete")
+                };
+                for relative in &changed {
+                    println!("{transfer_verb} '{relative}'");
+                }
+                for relative in &extra {
+                    println!("{delete_verb} '{relative}'");
+                }
+                if dry_run {
+                    return Ok(());
+                }
+
+                let connections = transfer::Connections::new(transfer.concurrency);
+                transfer::run_parallel(changed, transfer.concurrency, |relative| {
+                    let blob_path = transfer::join_blob_path(&prefix, &relative);
+                    if download {
+                        let path = transfer::confined_output(&local_root, &relative)?;
+                        download_blob_to(&client, &blob_path, &path, progress)
+                    } else {
+                        upload_file(
+                            &client,
+                            &local_root.join(&relative),
+                            &blob_path,
+                            &connections,
+                            options,
+                            progress,
+                        )
+                    }
+                })?;
+
+                for relative in extra {
+                    if download {
+                        let path = local_root.join(&relative);
+                        fs::remove_file(&path).map_err(|err| {
+                            format!("Failed to delete file '{}': {err:?}", path.display())
+                        })?;
+                    } else {
+                        let blob_path = transfer::join_blob_path(&prefix, &relative);
+                        client.delete_blob(&blob_path).map_err(|err| {
+                            format!("Failed to delete blob '{blob_path}': {err:?}")
+                        })?;
+                    }
+                }
+
+                Ok(())
+            }
+            Blob::Mv { src, dst, dry_run } => {
+                validate_blob_path(&src)?;
+                validate_blob_path(&dst)?;
+                if dry_run {
+                    println!("Would move '{src}' to '{dst}'");
+                    return Ok(());
+                }
+                client
+                    .move_blob(&src, &dst)
+                    .map_err(|err| format!("Failed to move blob '{src}': {err:?}"))?;
+                Ok(())
+            }
+            Blob::Cp { src, dst, dry_run } => {
+                validate_blob_path(&src)?;
+                validate_blob_path(&dst)?;
+                if dry_run {
+                    println!("Would copy '{src}' to '{dst}'");
+                    return Ok(());
+                }
+                client
+                    .copy_blob(&src, &dst)
+                    .map_err(|err| format!("Failed to copy blob '{src}': {err:?}"))?;
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum Runner {
+    /// Runner registration commands
+    #[command(subcommand)]
+    Registration(RunnerRegistration),
+
+    /// List runners
+    ///
+    /// Prints the ID, name, state, version, last heartbeat, current job and
+    /// labels of each runner, tab separated.
+    List {
+        /// only list runners that are offline
+        #[arg(long)]
+        offline: bool,
+    },
+
+    /// Show a runner
+    ///
+    /// Prints one tab separated key and value per line. Fails when the runner
+    /// does not exist.
+    Get {
+        #[arg(short, long, required = true)]
+        runner_id: Uuid,
+    },
+}
+
+impl Runner {
+    fn run<C, W>(self, client: C, stdout: &mut W) -> Result<()>
+    where
+        C: Client,
+        W: Write,
+    {
+        match self {
+            Runner::Registration(registration) => registration.run(client)?,
+            Runner::List { offline } => {
+                let runners = client
+                    .list_runners()
+                    .map_err(|err| format!("Failed to list runners: {err:?}"))?;
+
+                for runner in runners.iter().filter(|runner| !offline || !runner.online) {
+                    writeln!(
+                        stdout,
+                        "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                        runner.id,
+                        runner.name,
+                        runner_state(runner),
+                        runner.version,
+                        runner.last_heartbeat_at.as_deref().unwrap_or("-"),
+                        runner
+                            .current_job_id
+                            .map_or_else(|| "-".to_string(), |id| id.to_string()),
+                        runner.labels.join(","),
+                    )
+                    .map_err(|err| format!("Failed to write to stdout: {err:?}"))?;
+                }
+            }
+            Runner::Get { runner_id } => {
+                let runner = match client.get_runner(runner_id) {
+                    Ok(runner) => runner,
+                    Err(Error::NotFound) => {
+                        return Err(format!("Runner '{runner_id}' does not exist"));
+                    }
+                    Err(err) => return Err(format!("Failed to get runner: {err:?}")),
+                };
+
+                writeln!(
+                    stdout,
+                    "id\t{}\nname\t{}\nstate\t{}\nversion\t{}\nlast_heartbeat_at\t{}\ncurrent_job_id\t{}\nlabels\t{}",
+                    runner.id,
+                    runner.name,
+                    runner_state(&runner),
+                    runner.version,
+                    runner.last_heartbeat_at.as_deref().unwrap_or("-"),
+                    runner
+                        .current_job_id
+                        .map_or_else(|| "-".to_string(), |id| id.to_string()),
+                    runner.labels.join(","),
+                )
+                .map_err(|err| format!("Failed to write to stdout: {err:?}"))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn runner_state(runner: &RunnerInfo) -> &'static str {
+    if runner.online { "online" } else { "offline" }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+enum RunnerRegistration {
+    /// Get newly created runner registration token
+    #[command(name = "token")]
+    Token,
+
+    /// Get runner registration command with newly created token
+    #[command(name = "command")]
+    Command,
+}
+
+impl RunnerRegistration {
+    fn run<C>(self, client: C) -> Result<()>
+    where
+        C: Client,
+    {
+        match self {
+            RunnerRegistration::Token => {
+                let resp = client
+                    .create_runner_registration()
+                    .map_err(|err| format!("Failed to create runner registration: {err:?}"))?;
+
+                print!("{}", resp.token);
+            }
+            RunnerRegistration::Command => {
+                let resp = client
+                    .create_runner_registration()
+                    .map_err(|err| format!("Failed to create runner registration: {err:?}"))?;
+
+                println!(
+                    r#"runner configure --token "{}" --url "{}""#,
+                    resp.token, resp.url,
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod job_tests {
+    use super::*;
+    use crate::client::{
+        BlobInfo, Error as ClientError, JobInfo, MockClient, WorkflowRevision,
+        WorkflowRevisionResponse,
+    };
+    use mockall::predicate::*;
     use serde_json::Value;
     use uuid::Uuid;
 
     #[test]
-    fn test_download_failed() {
-        let job_id = Uuid::now_v7();
-        let artifact_name = "test.zip";
+    fn test_download_failed() {
+        let job_id = Uuid::now_v7();
+        let artifact_name = "test.zip";
+
+        let cmd = JobArtifact::Download {
+            job_id: Some(job_id),
+            latest: false,
+            workflow_id: None,
+            scan_name: None,
+            state: None,
+            artifact_name: artifact_name.to_string(),
+            output: None,
+            extract: false,
+            max_size: 1024 * 1024,
+        };
+        let mut client = MockClient::new();
+        client
+            .expect_download_job_artifact()
+            .with(eq(job_id), eq(artifact_name))
+            .times(1)
+            .returning(|_, _| Err(ClientError::Unauthorized));
+
+        let result = cmd.run(client, &Progress::hidden(), &mut io::sink());
+        assert!(result.is_err(), "expected error, got ok");
+    }
+
+    #[test]
+    fn test_download_latest_artifact() {
+        let workflow_id = Uuid::now_v7();
+        let expected = Uuid::now_v7();
+        let jobs = vec![
+            JobInfo {
+                id: Uuid::now_v7(),
+                scan_name: "recon".to_string(),
+                state: "succeeded".to_string(),
+                created_at: "2025-01-01T00:00:00Z".to_string(),
+                artifacts: vec!["subdomains.txt".to_string()],
+            },
+            JobInfo {
+                id: expected,
+                scan_name: "recon".to_string(),
+                state: "succeeded".to_string(),
+                created_at: "2025-01-03T00:00:00Z".to_string(),
+                artifacts: vec!["subdomains.txt".to_string()],
+            },
+            JobInfo {
+                id: Uuid::now_v7(),
+                scan_name: "recon".to_string(),
+                state: "succeeded".to_string(),
+                created_at: "2025-01-04T00:00:00Z".to_string(),
+                artifacts: vec!["ports.txt".to_string()],
+            },
+        ];
+
+        let cmd = JobArtifact::Download {
+            job_id: None,
+            latest: true,
+            workflow_id: Some(workflow_id),
+            scan_name: Some("recon".to_string()),
+            state: Some(JobState::Succeeded),
+            artifact_name: "subdomains.txt".to_string(),
+            output: Some("-".to_string()),
+            extract: false,
+            max_size: 1024 * 1024,
+        };
+
+        let mut client = MockClient::new();
+        client
+            .expect_list_scan_jobs()
+            .with(
+                eq(workflow_id),
+                eq("recon"),
+                eq(Some("succeeded".to_string())),
+            )
+            .times(1)
+            .returning(move |_, _, _| Ok(jobs.clone()));
+        client
+            .expect_download_job_artifact()
+            .with(eq(expected), eq("subdomains.txt"))
+            .times(1)
+            .returning(|_, _| Ok((Box::new(io::Cursor::new(Vec::new())), None)));
+
+        let result = cmd.run(client, &Progress::hidden(), &mut io::sink());
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+    }
+
+    #[test]
+    fn test_download_latest_artifact_missing() {
+        let cmd = JobArtifact::Download {
+            job_id: None,
+            latest: true,
+            workflow_id: Some(Uuid::now_v7()),
+            scan_name: Some("recon".to_string()),
+            state: None,
+            artifact_name: "subdomains.txt".to_string(),
+            output: Some("-".to_string()),
+            extract: false,
+            max_size: 1024 * 1024,
+        };
+
+        let mut client = MockClient::new();
+        client
+            .expect_list_scan_jobs()
+            .times(1)
+            .returning(|_, _, _| Ok(vec![]));
+        client.expect_download_job_artifact().times(0);
+
+        let result = cmd.run(client, &Progress::hidden(), &mut io::sink());
+        assert!(result.is_err(), "expected error, got ok");
+    }
+
+    #[test]
+    fn test_artifact_diff() {
+        let from_job = Uuid::now_v7();
+        let to_job = Uuid::now_v7();
+        let cmd = JobArtifact::Diff {
+            from_job,
+            to_job,
+            artifact_name: "subdomains.txt".to_string(),
+            format: DiffFormat::Auto,
+        };
+
+        let mut client = MockClient::new();
+        client
+            .expect_download_job_artifact()
+            .with(eq(from_job), eq("subdomains.txt"))
+            .times(1)
+            .returning(|_, _| Ok((Box::new(io::Cursor::new(b"a.example.com\n".to_vec())), None)));
+        client
+            .expect_download_job_artifact()
+            .with(eq(to_job), eq("subdomains.txt"))
+            .times(1)
+            .returning(|_, _| {
+                Ok((
+                    Box::new(io::Cursor::new(b"a.example.com\nb.example.com\n".to_vec())),
+                    None,
+                ))
+            });
+
+        let result = cmd.run(client, &Progress::hidden(), &mut io::sink());
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+    }
+
+    #[test]
+    fn test_artifact_ls_archive() {
+        let job_id = Uuid::now_v7();
+        let url = "https://files.example.com/archive.zip";
+
+        let new_client = || {
+            let mut client = MockClient::new();
+            client
+                .expect_get_job_artifact_url()
+                .with(eq(job_id), eq("archive.zip"), eq(None))
+                .times(1)
+                .returning(|_, _, _| Ok(url.to_string()));
+            // An archive without entries is just the end of central directory record
+            client
+                .expect_download_url_tail()
+                .withf(move |u, _| u == url)
+                .times(1)
+                .returning(|_, _| {
+                    let mut eocd = vec![0x50, 0x4b, 0x05, 0x06];
+                    eocd.resize(22, 0);
+                    Ok((eocd, 22))
+                });
+            client.expect_download_url_range().never();
+            client
+        };
+        let cmd = |extract: Option<&str>| JobArtifact::LsArchive {
+            job_id,
+            artifact_name: "archive.zip".to_string(),
+            extract: extract.map(str::to_string),
+            output: None,
+        };
+
+        let result = cmd(None).run(new_client(), &Progress::hidden(), &mut io::sink());
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+
+        let result =
+            cmd(Some("missing.txt")).run(new_client(), &Progress::hidden(), &mut io::sink());
+        assert!(result.is_err(), "expected error, got {result:?}");
+    }
+
+    #[test]
+    fn test_delete_job_call() {
+        let job_id = Uuid::now_v7();
+
+        let cmd = Job::Delete { job_id };
+
+        let mut client = MockClient::new();
+        client
+            .expect_delete_job()
+            .with(eq(job_id))
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let result = cmd.run(client, &Progress::hidden(), &mut io::sink());
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+    }
+
+    #[test]
+    fn test_dispatch_call_no_inputs() {
+        let revision_id = Uuid::now_v7();
+        let cmd = Scan::Dispatch {
+            workflow_id: revision_id,
+            scan_name: "example".to_string(),
+            input_string: None,
+            input_bool: None,
+        };
+
+        let mut client = MockClient::new();
+        client
+            .expect_dispatch_scan()
+            .with(
+                eq(revision_id),
+                function(|v| v == "example"),
+                function(|v: &Option<BTreeMap<String, Value>>| v.is_none()),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let result = cmd.run(client);
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+    }
+
+    #[test]
+    fn test_dispatch_call_with_inputs() {
+        let revision_id = Uuid::now_v7();
+        let cmd = Scan::Dispatch {
+            workflow_id: revision_id,
+            scan_name: "example".to_string(),
+            input_string: Some(vec!["s_key=s_val".to_string()]),
+            input_bool: Some(vec!["b_key=true".to_string()]),
+        };
+
+        let mut client = MockClient::new();
+        client
+            .expect_dispatch_scan()
+            .with(
+                eq(revision_id),
+                function(|v| v == "example"),
+                function(|v: &Option<BTreeMap<String, Value>>| match v {
+                    None => false,
+                    Some(input) => {
+                        match input.get("s_key").expect("s_key to exist").to_owned() {
+                            Value::String(val) => val == *"s_val",
+                            _ => return false,
+                        };
+                        match input.get("b_key").expect("b_key to exist").to_owned() {
+                            Value::Bool(val) => val,
+                            _ => false,
+                        }
+                    }
+                }),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let result = cmd.run(client);
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+    }
+
+    #[test]
+    fn test_split_inputs() {
+        let input = "k=v";
+        let result = split_input(input).unwrap_or_else(|_| panic!("{input}: want ok, got err"));
+        assert_eq!(result.0, "k");
+        assert_eq!(result.1, "v");
+
+        let input = "test";
+        let result = split_input(input);
+        assert!(result.is_err(), "expected error, got {result:?}");
+
+        let input = "k=v=a";
+        let result = split_input(input).unwrap_or_else(|_| panic!("{input}: want ok, got err"));
+        assert_eq!(result.0, "k");
+        assert_eq!(result.1, "v=a");
+    }
+
+    #[test]
+    fn test_workflow_push_revision() {
+        let workflow_id = Uuid::now_v7();
+        let file = env::temp_dir().join(format!("{}.yaml", Uuid::now_v7()));
+        fs::write(&file, "scans: {}\n").expect("failed to write workflow file");
+
+        let cmd = Workflow::Push {
+            workflow_id: Some(workflow_id),
+            file: file.to_string_lossy().to_string(),
+            create: false,
+            name: None,
+        };
+
+        let mut client = MockClient::new();
+        client
+            .expect_create_workflow_revision()
+            .with(eq(workflow_id), function(|v: &String| v == "scans: {}\n"))
+            .times(1)
+            .returning(move |_, _| {
+                Ok(WorkflowRevisionResponse {
+                    workflow_id,
+                    revision_id: Uuid::now_v7(),
+                })
+            });
+        client.expect_create_workflow().times(0);
+
+        let result = cmd.run(client);
+        fs::remove_file(&file).ok();
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+    }
+
+    #[test]
+    fn test_workflow_push_create() {
+        let file = env::temp_dir().join(format!("{}.yaml", Uuid::now_v7()));
+        fs::write(&file, "scans: {}\n").expect("failed to write workflow file");
+
+        let cmd = Workflow::Push {
+            workflow_id: None,
+            file: file.to_string_lossy().to_string(),
+            create: true,
+            name: Some("recon".to_string()),
+        };
+
+        let mut client = MockClient::new();
+        client
+            .expect_create_workflow()
+            .with(function(|v: &String| v == "recon"), always())
+            .times(1)
+            .returning(|_, _| {
+                Ok(WorkflowRevisionResponse {
+                    workflow_id: Uuid::now_v7(),
+                    revision_id: Uuid::now_v7(),
+                })
+            });
+        client.expect_create_workflow_revision().times(0);
+
+        let result = cmd.run(client);
+        fs::remove_file(&file).ok();
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+    }
+
+    #[test]
+    fn test_workflow_validate() {
+        let valid = env::temp_dir().join(format!("{}.yaml", Uuid::now_v7()));
+        fs::write(
+            &valid,
+            "scans:\n  example:\n    jobs:\n      a:\n        steps:\n          - run: echo\n",
+        )
+        .expect("failed to write workflow file");
+        let invalid = env::temp_dir().join(format!("{}.yaml", Uuid::now_v7()));
+        fs::write(&invalid, "scans:\n  bad-name: {}\n").expect("failed to write workflow file");
+
+        let result = WorkflowValidate {
+            files: vec![valid.to_string_lossy().to_string()],
+        }
+        .run();
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+
+        let result = WorkflowValidate {
+            files: vec![
+                valid.to_string_lossy().to_string(),
+                invalid.to_string_lossy().to_string(),
+            ],
+        }
+        .run();
+        fs::remove_file(&valid).ok();
+        fs::remove_file(&invalid).ok();
+        assert!(result.is_err(), "expected error, got ok");
+    }
+
+    #[test]
+    fn test_workflow_revisions_diff() {
+        let workflow_id = Uuid::now_v7();
+        let from = Uuid::now_v7();
+        let to = Uuid::now_v7();
+
+        let mut client = MockClient::new();
+        client
+            .expect_get_workflow_revision_source()
+            .with(eq(workflow_id), eq(from))
+            .times(1)
+            .returning(|_, _| {
+                Ok(
+                    "scans:\n  a:\n    jobs:\n      x:\n        steps:\n          - run: echo\n"
+                        .to_string(),
+                )
+            });
+        client
+            .expect_get_workflow_revision_source()
+            .with(eq(workflow_id), eq(to))
+            .times(1)
+            .returning(|_, _| Err(ClientError::NotFound));
+
+        let cmd = WorkflowRevisions::Diff {
+            workflow_id,
+            from,
+            to,
+        };
+        let result = cmd.run(client);
+        assert!(result.is_err(), "expected error, got ok");
+    }
+
+    #[test]
+    fn test_revision_diff() {
+        let from = Uuid::now_v7();
+        let to = Uuid::now_v7();
+        let from_source =
+            "scans:\n  a:\n    jobs:\n      x:\n        steps:\n          - run: echo\n";
+        let to_source =
+            "scans:\n  a:\n    jobs:\n      y:\n        steps:\n          - run: echo\n";
+
+        let (diff, warning) = revision_diff(from_source, to_source, from, to);
+        assert_eq!(warning, None);
+        assert_eq!(
+            diff,
+            format!(
+                "- job a.x\n+ job a.y\n\n--- {from}\n+++ {to}\n@@ -1,6 +1,6 @@\n scans:\n   a:\n     jobs:\n-      x:\n+      y:\n         steps:\n           - run: echo\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_revision_diff_invalid() {
+        let from = Uuid::now_v7();
+        let to = Uuid::now_v7();
+        let from_source = "scans:\n  a:\n    jobs: [\n";
+        let to_source =
+            "scans:\n  a:\n    jobs:\n      y:\n        steps:\n          - run: echo\n";
+
+        let (diff, warning) = revision_diff(from_source, to_source, from, to);
+        assert_eq!(
+            warning,
+            Some(format!(
+                "revision {from} has problems, skipping the summary of changes"
+            ))
+        );
+        assert!(
+            diff.starts_with(&format!("--- {from}\n+++ {to}\n")),
+            "expected only the line diff, got {diff}"
+        );
+    }
+
+    #[test]
+    fn test_workflow_graph_latest_revision() {
+        let workflow_id = Uuid::now_v7();
+        let old = Uuid::now_v7();
+        let latest = Uuid::now_v7();
+
+        let mut client = MockClient::new();
+        client
+            .expect_list_workflow_revisions()
+            .with(eq(workflow_id))
+            .times(1)
+            .returning(move |_| {
+                Ok(vec![
+                    WorkflowRevision {
+                        id: latest,
+                        created_at: "2025-02-01T00:00:00Z".to_string(),
+                    },
+                    WorkflowRevision {
+                        id: old,
+                        created_at: "2025-01-01T00:00:00Z".to_string(),
+                    },
+                ])
+            });
+        client
+            .expect_get_workflow_revision_source()
+            .with(eq(workflow_id), eq(latest))
+            .times(1)
+            .returning(|_, _| {
+                Ok(
+                    "scans:\n  a:\n    jobs:\n      x:\n        steps:\n          - run: echo\n"
+                        .to_string(),
+                )
+            });
+
+        let cmd = WorkflowGraph {
+            file: None,
+            workflow_id: Some(workflow_id),
+            revision_id: None,
+            format: GraphFormat::Mermaid,
+        };
+        let result = cmd.run(|| Ok(client));
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+    }
+
+    #[test]
+    fn test_workflow_graph_local_file() {
+        let file = env::temp_dir().join(format!("{}.yaml", Uuid::now_v7()));
+        fs::write(&file, "scans:\n  bad-name: {}\n").expect("failed to write workflow file");
+
+        let cmd = WorkflowGraph {
+            file: Some(file.to_string_lossy().to_string()),
+            workflow_id: None,
+            revision_id: None,
+            format: GraphFormat::Dot,
+        };
+        let result = cmd.run(|| -> Result<MockClient> { panic!("client must not be created") });
+        fs::remove_file(&file).ok();
+        assert!(result.is_err(), "expected error, got ok");
+    }
+
+    #[test]
+    fn test_workflow_init() {
+        let output = env::temp_dir().join(format!("{}.yaml", Uuid::now_v7()));
+        let cmd = WorkflowInit {
+            template: "port-scan".to_string(),
+            var: Some(vec!["scan_name=ports_weekly".to_string()]),
+            output: output.to_string_lossy().to_string(),
+            force: false,
+            no_input: true,
+        };
+
+        let result = cmd.run();
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+        let source = fs::read_to_string(&output).expect("workflow file to exist");
+        assert!(source.contains("ports_weekly:"));
+
+        let result = cmd.run();
+        fs::remove_file(&output).ok();
+        assert!(result.is_err(), "expected error for existing file, got ok");
+    }
+
+    #[test]
+    fn test_prompt_template_vars() {
+        let template = template::get("subdomains").expect("template to exist");
+        let mut vars = BTreeMap::from([("cron".to_string(), "0 1 * * *".to_string())]);
+        let mut input = io::Cursor::new("recon\n\n");
+        let mut output = Vec::new();
+
+        let result = prompt_template_vars(template, &mut vars, &mut input, &mut output);
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+        assert_eq!(
+            vars,
+            BTreeMap::from([
+                ("cron".to_string(), "0 1 * * *".to_string()),
+                ("scan_name".to_string(), "recon".to_string()),
+            ])
+        );
+        assert_eq!(
+            String::from_utf8_lossy(&output),
+            "Name of the scan [subdomains]: Root domain to enumerate [example.com]: "
+        );
+    }
+
+    #[test]
+    fn test_workflow_vars_set_invalid_key() {
+        let cmd = WorkflowVars::Set {
+            workflow_id: Uuid::now_v7(),
+            key: "bad key".to_string(),
+            value: "value".to_string(),
+        };
+
+        let mut client = MockClient::new();
+        client.expect_set_workflow_var().times(0);
+
+        let result = cmd.run(client);
+        assert!(result.is_err(), "expected error, got ok");
+    }
+
+    #[test]
+    fn test_workflow_secrets_set_from_stdin() {
+        let workflow_id = Uuid::now_v7();
+        let cmd = WorkflowSecrets::Set {
+            workflow_id,
+            key: "API_KEY".to_string(),
+            file: None,
+        };
+
+        let mut client = MockClient::new();
+        client
+            .expect_set_workflow_secret()
+            .with(
+                eq(workflow_id),
+                eq("API_KEY"),
+                function(|v: &String| v == " s3cr3t "),
+            )
+            .times(1)
+            .returning(|_, _, _| Ok(()));
+
+        let result = cmd.run(client, &mut io::Cursor::new(" s3cr3t \n"));
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+    }
+
+    #[test]
+    fn test_workflow_secrets_set_empty() {
+        let cmd = WorkflowSecrets::Set {
+            workflow_id: Uuid::now_v7(),
+            key: "API_KEY".to_string(),
+            file: None,
+        };
+
+        let mut client = MockClient::new();
+        client.expect_set_workflow_secret().times(0);
+
+        let result = cmd.run(client, &mut io::Cursor::new("\n"));
+        assert!(result.is_err(), "expected error, got ok");
+    }
+
+    #[test]
+    fn test_list_all_blobs_pagination() {
+        let mut client = MockClient::new();
+        client
+            .expect_list_blobs()
+            .with(eq("wordlists/"), eq(true), eq(None))
+            .times(1)
+            .returning(|_, _, _| {
+                Ok(BlobPage {
+                    blobs: vec![BlobInfo {
+                        path: "wordlists/a.txt".to_string(),
+                        size: 10,
+                        modified_at: "2025-01-01T00:00:00Z".to_string(),
+                        hash: "abc".to_string(),
+                        plaintext_hash: None,
+                    }],
+                    prefixes: vec![],
+                    next_cursor: Some("next".to_string()),
+                })
+            });
+        client
+            .expect_list_blobs()
+            .with(eq("wordlists/"), eq(true), eq(Some("next".to_string())))
+            .times(1)
+            .returning(|_, _, _| {
+                Ok(BlobPage {
+                    blobs: vec![BlobInfo {
+                        path: "wordlists/nested/b.txt".to_string(),
+                        size: 20,
+                        modified_at: "2025-01-02T00:00:00Z".to_string(),
+                        hash: "def".to_string(),
+                        plaintext_hash: None,
+                    }],
+                    prefixes: vec![],
+                    next_cursor: None,
+                })
+            });
+
+        let listing = list_all_blobs(&client, "wordlists/", true)
+            .unwrap_or_else(|err| panic!("expected ok, got {err}"));
+        let paths = listing
+            .blobs
+            .iter()
+            .map(|b| b.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["wordlists/a.txt", "wordlists/nested/b.txt"]);
+    }
+
+    #[test]
+    fn test_blob_rm_recursive() {
+        let cmd = Blob::Rm {
+            path: "old/".to_string(),
+            recursive: true,
+            dry_run: false,
+        };
+
+        let mut client = MockClient::new();
+        client
+            .expect_list_blobs()
+            .with(eq("old/"), eq(true), eq(None))
+            .times(1)
+            .returning(|_, _, _| {
+                Ok(BlobPage {
+                    blobs: ["old/a.txt", "old/b/c.txt"]
+                        .iter()
+                        .map(|path| BlobInfo {
+                            path: path.to_string(),
+                            size: 1,
+                            modified_at: "2025-01-01T00:00:00Z".to_string(),
+                            hash: "abc".to_string(),
+                            plaintext_hash: None,
+                        })
+                        .collect(),
+                    prefixes: vec![],
+                    next_cursor: None,
+                })
+            });
+        client
+            .expect_delete_blob()
+            .with(eq("old/a.txt"))
+            .times(1)
+            .returning(|_| Ok(()));
+        client
+            .expect_delete_blob()
+            .with(eq("old/b/c.txt"))
+            .times(1)
+            .returning(|_| Ok(()));
+
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::empty(),
+            &mut io::sink(),
+        );
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+    }
+
+    #[test]
+    fn test_blob_rm_recursive_directory_only() {
+        let cmd = Blob::Rm {
+            path: "old".to_string(),
+            recursive: true,
+            dry_run: false,
+        };
+
+        let mut client = MockClient::new();
+        client
+            .expect_list_blobs()
+            .with(eq("old/"), eq(true), eq(None))
+            .times(1)
+            .returning(|_, _, _| {
+                Ok(BlobPage {
+                    blobs: ["old/a.txt", "older/b.txt"]
+                        .iter()
+                        .map(|path| BlobInfo {
+                            path: path.to_string(),
+                            size: 1,
+                            modified_at: "2025-01-01T00:00:00Z".to_string(),
+                            hash: "abc".to_string(),
+                            plaintext_hash: None,
+                        })
+                        .collect(),
+                    prefixes: vec![],
+                    next_cursor: None,
+                })
+            });
+        client
+            .expect_delete_blob()
+            .with(eq("old/a.txt"))
+            .times(1)
+            .returning(|_| Ok(()));
+        client.expect_delete_blob().with(eq("older/b.txt")).times(0);
+
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::empty(),
+            &mut io::sink(),
+        );
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+
+        for path in ["", "/"] {
+            let cmd = Blob::Rm {
+                path: path.to_string(),
+                recursive: true,
+                dry_run: false,
+            };
+            let mut client = MockClient::new();
+            client.expect_list_blobs().times(0);
+            client.expect_delete_blob().times(0);
+
+            let result = cmd.run(
+                client,
+                &Progress::hidden(),
+                &mut io::empty(),
+                &mut io::sink(),
+            );
+            assert!(result.is_err(), "expected error for '{path}', got ok");
+        }
+    }
+
+    #[test]
+    fn test_blob_rm_dry_run() {
+        let cmd = Blob::Rm {
+            path: "a.txt".to_string(),
+            recursive: false,
+            dry_run: true,
+        };
+
+        let mut client = MockClient::new();
+        client.expect_delete_blob().times(0);
+
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::empty(),
+            &mut io::sink(),
+        );
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+    }
+
+    #[test]
+    fn test_blob_mv_dry_run() {
+        let cmd = Blob::Mv {
+            src: "a.txt".to_string(),
+            dst: "b.txt".to_string(),
+            dry_run: true,
+        };
+
+        let mut client = MockClient::new();
+        client.expect_move_blob().times(0);
+
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::empty(),
+            &mut io::sink(),
+        );
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+    }
+
+    #[test]
+    fn test_move_and_copy_blob_invalid_path() {
+        for (src, dst) in [
+            ("a.txt", "../b.txt"),
+            ("a/../b.txt", "b.txt"),
+            ("a.txt", ""),
+        ] {
+            let cmds = [
+                Blob::Mv {
+                    src: src.to_string(),
+                    dst: dst.to_string(),
+                    dry_run: false,
+                },
+                Blob::Cp {
+                    src: src.to_string(),
+                    dst: dst.to_string(),
+                    dry_run: false,
+                },
+            ];
+            for cmd in cmds {
+                let mut client = MockClient::new();
+                client.expect_move_blob().times(0);
+                client.expect_copy_blob().times(0);
+
+                let result = cmd.run(
+                    client,
+                    &Progress::hidden(),
+                    &mut io::empty(),
+                    &mut io::sink(),
+                );
+                assert!(
+                    result.is_err(),
+                    "expected error for '{src}' to '{dst}', got ok"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_upload_blob_directory() {
+        let root = env::temp_dir().join(Uuid::now_v7().to_string());
+        fs::create_dir_all(root.join("nested")).expect("failed to create dirs");
+        fs::write(root.join("a.txt"), "a").expect("failed to write file");
+        fs::write(root.join("nested/b.txt"), "b").expect("failed to write file");
+        fs::write(root.join("nested/c.log"), "c").expect("failed to write file");
+
+        let cmd = Blob::Upload {
+            src: root.to_string_lossy().to_string(),
+            dst: "backup/".to_string(),
+            compress: None,
+            encrypt: false,
+            transfer: TransferOptions {
+                include: vec![],
+                exclude: vec!["**/*.log".to_string()],
+                concurrency: 2,
+            },
+        };
+
+        let mut client = MockClient::new();
+        client
+            .expect_upload_blob_file()
+            .with(
+                always(),
+                always(),
+                eq("backup/a.txt"),
+                eq(UploadOptions::default()),
+                eq(None),
+            )
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(()));
+        client
+            .expect_upload_blob_file()
+            .with(
+                always(),
+                eq(1),
+                eq("backup/nested/b.txt"),
+                eq(UploadOptions::default()),
+                eq(None),
+            )
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(()));
+
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::empty(),
+            &mut io::sink(),
+        );
+        fs::remove_dir_all(&root).ok();
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+    }
+
+    #[test]
+    fn test_download_blob_prefix() {
+        let root = env::temp_dir().join(Uuid::now_v7().to_string());
 
-        let cmd = JobArtifact::Download {
-            job_id,
-            artifact_name: artifact_name.to_string(),
-            output: None,
+        let cmd = Blob::Download {
+            src: "backup".to_string(),
+            dst: Some(root.to_string_lossy().to_string()),
+            recursive: true,
+            extract: false,
+            max_size: 1024 * 1024,
+            transfer: TransferOptions {
+                include: vec!["**/*.txt".to_string()],
+                exclude: vec![],
+                concurrency: 2,
+            },
         };
+
         let mut client = MockClient::new();
         client
-            .expect_download_job_artifact()
-            .with(eq(job_id), eq(artifact_name))
+            .expect_list_blobs()
+            .with(eq("backup/"), eq(true), eq(None))
             .times(1)
-            .returning(|_, _| Err(ClientError::Unauthorized));
+            .returning(|_, _, _| {
+                Ok(BlobPage {
+                    blobs: ["backup/a.txt", "backup/nested/b.txt", "backup/c.log"]
+                        .iter()
+                        .map(|path| BlobInfo {
+                            path: path.to_string(),
+                            size: 1,
+                            modified_at: "2025-01-01T00:00:00Z".to_string(),
+                            hash: "abc".to_string(),
+                            plaintext_hash: None,
+                        })
+                        .collect(),
+                    prefixes: vec![],
+                    next_cursor: None,
+                })
+            });
+        client
+            .expect_download_blob_file()
+            .with(function(|p: &str| p.ends_with(".txt")))
+            .times(2)
+            .returning(|p| Ok((Box::new(io::Cursor::new(p.to_string().into_bytes())), None)));
 
-        let result = cmd.run(client);
-        assert!(result.is_err(), "expected error, got ok");
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::empty(),
+            &mut io::sink(),
+        );
+        let a = fs::read_to_string(root.join("a.txt"));
+        let b = fs::read_to_string(root.join("nested/b.txt"));
+        fs::remove_dir_all(&root).ok();
+
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+        assert_eq!(a.ok().as_deref(), Some("backup/a.txt"));
+        assert_eq!(b.ok().as_deref(), Some("backup/nested/b.txt"));
     }
 
     #[test]
-    fn test_delete_job_call() {
-        let job_id = Uuid::now_v7();
+    fn test_blob_sync_upload() {
+        let root = env::temp_dir().join(Uuid::now_v7().to_string());
+        fs::create_dir_all(&root).expect("failed to create dir");
+        fs::write(root.join("same.txt"), "hello").expect("failed to write file");
+        fs::write(root.join("changed.txt"), "hello").expect("failed to write file");
+        fs::write(root.join("new.txt"), "new").expect("failed to write file");
 
-        let cmd = Job::Delete { job_id };
+        let cmd = Blob::Sync {
+            local: root.to_string_lossy().to_string(),
+            remote: "lists".to_string(),
+            download: false,
+            delete: true,
+            dry_run: false,
+            compress: None,
+            encrypt: false,
+            transfer: TransferOptions {
+                include: vec![],
+                exclude: vec![],
+                concurrency: 2,
+            },
+        };
 
+        let hello = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
         let mut client = MockClient::new();
         client
-            .expect_delete_job()
-            .with(eq(job_id))
+            .expect_list_blobs()
+            .with(eq("lists/"), eq(true), eq(None))
+            .times(1)
+            .returning(move |_, _, _| {
+                Ok(BlobPage {
+                    blobs: [
+                        ("lists/same.txt", hello),
+                        ("lists/changed.txt", "0000"),
+                        ("lists/extra.txt", "0000"),
+                    ]
+                    .iter()
+                    .map(|(path, hash)| BlobInfo {
+                        path: path.to_string(),
+                        size: 5,
+                        modified_at: "2025-01-01T00:00:00Z".to_string(),
+                        hash: hash.to_string(),
+                        plaintext_hash: None,
+                    })
+                    .collect(),
+                    prefixes: vec![],
+                    next_cursor: None,
+                })
+            });
+        client
+            .expect_upload_blob_file()
+            .with(
+                always(),
+                always(),
+                eq("lists/changed.txt"),
+                eq(UploadOptions::default()),
+                eq(None),
+            )
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(()));
+        client
+            .expect_upload_blob_file()
+            .with(
+                always(),
+                eq(3),
+                eq("lists/new.txt"),
+                eq(UploadOptions::default()),
+                eq(None),
+            )
+            .times(1)
+            .returning(|_, _, _, _, _| Ok(()));
+        client
+            .expect_delete_blob()
+            .with(eq("lists/extra.txt"))
             .times(1)
             .returning(|_| Ok(()));
 
-        let result = cmd.run(client);
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::empty(),
+            &mut io::sink(),
+        );
+        fs::remove_dir_all(&root).ok();
         assert!(result.is_ok(), "expected ok, got {result:?}");
     }
 
     #[test]
-    fn test_dispatch_call_no_inputs() {
-        let revision_id = Uuid::now_v7();
-        let cmd = Scan::Dispatch {
-            workflow_id: revision_id,
-            scan_name: "example".to_string(),
-            input_string: None,
-            input_bool: None,
+    fn test_blob_sync_upload_compressed() {
+        let root = env::temp_dir().join(Uuid::now_v7().to_string());
+        fs::create_dir_all(&root).expect("failed to create dir");
+        fs::write(root.join("same.txt"), "hello").expect("failed to write file");
+        fs::write(root.join("changed.txt"), "hello").expect("failed to write file");
+
+        let cmd = Blob::Sync {
+            local: root.to_string_lossy().to_string(),
+            remote: "lists".to_string(),
+            download: false,
+            delete: false,
+            dry_run: false,
+            compress: Some(ContentEncoding::Gzip),
+            encrypt: false,
+            transfer: TransferOptions {
+                include: vec![],
+                exclude: vec![],
+                concurrency: 2,
+            },
         };
 
+        // Stored size and hash are the ones of the compressed content
+        let hello = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
         let mut client = MockClient::new();
         client
-            .expect_dispatch_scan()
+            .expect_list_blobs()
+            .with(eq("lists/"), eq(true), eq(None))
+            .times(1)
+            .returning(move |_, _, _| {
+                Ok(BlobPage {
+                    blobs: [("lists/same.txt", hello), ("lists/changed.txt", "0000")]
+                        .iter()
+                        .map(|(path, plaintext_hash)| BlobInfo {
+                            path: path.to_string(),
+                            size: 25,
+                            modified_at: "2025-01-01T00:00:00Z".to_string(),
+                            hash: "1111".to_string(),
+                            plaintext_hash: Some(plaintext_hash.to_string()),
+                        })
+                        .collect(),
+                    prefixes: vec![],
+                    next_cursor: None,
+                })
+            });
+        client
+            .expect_upload_blob_file()
             .with(
-                eq(revision_id),
-                function(|v| v == "example"),
-                function(|v: &Option<BTreeMap<String, Value>>| v.is_none()),
+                always(),
+                always(),
+                eq("lists/changed.txt"),
+                eq(UploadOptions {
+                    encoding: Some(ContentEncoding::Gzip),
+                    encrypt: false,
+                }),
+                eq(Some(hello.to_string())),
             )
             .times(1)
-            .returning(|_, _, _| Ok(()));
+            .returning(|_, _, _, _, _| Ok(()));
 
-        let result = cmd.run(client);
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::empty(),
+            &mut io::sink(),
+        );
+        fs::remove_dir_all(&root).ok();
         assert!(result.is_ok(), "expected ok, got {result:?}");
     }
 
     #[test]
-    fn test_dispatch_call_with_inputs() {
-        let revision_id = Uuid::now_v7();
-        let cmd = Scan::Dispatch {
-            workflow_id: revision_id,
-            scan_name: "example".to_string(),
-            input_string: Some(vec!["s_key=s_val".to_string()]),
-            input_bool: Some(vec!["b_key=true".to_string()]),
+    fn test_blob_sync_download() {
+        let root = env::temp_dir().join(Uuid::now_v7().to_string());
+        fs::create_dir_all(&root).expect("failed to create dir");
+        fs::write(root.join("stale.txt"), "stale").expect("failed to write file");
+
+        let cmd = Blob::Sync {
+            local: root.to_string_lossy().to_string(),
+            remote: "lists/".to_string(),
+            download: true,
+            delete: false,
+            dry_run: false,
+            compress: None,
+            encrypt: false,
+            transfer: TransferOptions {
+                include: vec![],
+                exclude: vec![],
+                concurrency: 2,
+            },
         };
 
         let mut client = MockClient::new();
+        client.expect_list_blobs().times(1).returning(|_, _, _| {
+            Ok(BlobPage {
+                blobs: vec![BlobInfo {
+                    path: "lists/nested/new.txt".to_string(),
+                    size: 3,
+                    modified_at: "2025-01-01T00:00:00Z".to_string(),
+                    hash: "0000".to_string(),
+                    plaintext_hash: None,
+                }],
+                prefixes: vec![],
+                next_cursor: None,
+            })
+        });
         client
-            .expect_dispatch_scan()
-            .with(
-                eq(revision_id),
-                function(|v| v == "example"),
-                function(|v: &Option<BTreeMap<String, Value>>| match v {
-                    None => false,
-                    Some(input) => {
-                        match input.get("s_key").expect("s_key to exist").to_owned() {
-                            Value::String(val) => val == *"s_val",
-                            _ => return false,
-                        };
-                        match input.get("b_key").expect("b_key to exist").to_owned() {
-                            Value::Bool(val) => val,
-                            _ => false,
-                        }
-                    }
-                }),
-            )
+            .expect_download_blob_file()
+            .with(eq("lists/nested/new.txt"))
             .times(1)
-            .returning(|_, _, _| Ok(()));
+            .returning(|_| Ok((Box::new(io::Cursor::new(b"new".to_vec())), None)));
+
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::empty(),
+            &mut io::sink(),
+        );
+        let new = fs::read_to_string(root.join("nested/new.txt"));
+        let stale_kept = root.join("stale.txt").exists();
+        fs::remove_dir_all(&root).ok();
 
-        let result = cmd.run(client);
         assert!(result.is_ok(), "expected ok, got {result:?}");
+        assert_eq!(new.ok().as_deref(), Some("new"));
+        assert!(stale_kept, "files must not be deleted without --delete");
     }
 
     #[test]
-    fn test_split_inputs() {
-        let input = "k=v";
-        let result = split_input(input).unwrap_or_else(|_| panic!("{input}: want ok, got err"));
-        assert_eq!(result.0, "k");
-        assert_eq!(result.1, "v");
+    fn test_download_artifact_to_stdout() {
+        let job_id = Uuid::now_v7();
+        let cmd = JobArtifact::Download {
+            job_id: Some(job_id),
+            latest: false,
+            workflow_id: None,
+            scan_name: None,
+            state: None,
+            artifact_name: "subdomains.txt".to_string(),
+            output: Some("-".to_string()),
+            extract: false,
+            max_size: 1024 * 1024,
+        };
 
-        let input = "test";
-        let result = split_input(input);
-        assert!(result.is_err(), "expected error, got {result:?}");
+        let mut client = MockClient::new();
+        client
+            .expect_download_job_artifact()
+            .with(eq(job_id), eq("subdomains.txt"))
+            .times(1)
+            .returning(|_, _| Ok((Box::new(io::Cursor::new(b"a.example.com\n".to_vec())), None)));
 
-        let input = "k=v=a";
-        let result = split_input(input).unwrap_or_else(|_| panic!("{input}: want ok, got err"));
-        assert_eq!(result.0, "k");
-        assert_eq!(result.1, "v=a");
+        let mut stdout = Vec::new();
+        let result = cmd.run(client, &Progress::hidden(), &mut stdout);
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+        assert_eq!(stdout, b"a.example.com\n");
+        assert!(
+            !Path::new("-").exists(),
+            "expected stdout, not a file named '-'"
+        );
+    }
+
+    #[test]
+    fn test_download_artifact_extract() {
+        let job_id = Uuid::now_v7();
+        let root = env::temp_dir().join(Uuid::now_v7().to_string());
+
+        let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(14);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "hosts/a.txt", &b"a.example.com\n"[..])
+            .expect("failed to append to tar");
+        let archive = builder
+            .into_inner()
+            .and_then(|encoder| encoder.finish())
+            .expect("failed to build tar.gz");
+
+        let cmd = |max_size| JobArtifact::Download {
+            job_id: Some(job_id),
+            latest: false,
+            workflow_id: None,
+            scan_name: None,
+            state: None,
+            artifact_name: "results.tar.gz".to_string(),
+            output: Some(root.to_string_lossy().to_string()),
+            extract: true,
+            max_size,
+        };
+        let client = || {
+            let archive = archive.clone();
+            let mut client = MockClient::new();
+            client
+                .expect_download_job_artifact()
+                .with(eq(job_id), eq("results.tar.gz"))
+                .times(1)
+                .returning(move |_, _| Ok((Box::new(io::Cursor::new(archive.clone())), None)));
+            client
+        };
+
+        let too_large = cmd(10).run(client(), &Progress::hidden(), &mut io::sink());
+        let cleaned_up = !root.exists();
+        let result = cmd(1024 * 1024).run(client(), &Progress::hidden(), &mut io::sink());
+        let content = fs::read_to_string(root.join("hosts/a.txt"));
+        fs::remove_dir_all(&root).ok();
+
+        assert!(too_large.is_err(), "expected error, got ok");
+        assert!(cleaned_up, "expected extracted files to be removed");
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+        assert_eq!(content.ok().as_deref(), Some("a.example.com\n"));
+    }
+
+    #[test]
+    fn test_download_blob_recursive_to_stdout() {
+        let cmd = Blob::Download {
+            src: "dir/".to_string(),
+            dst: Some("-".to_string()),
+            recursive: true,
+            extract: false,
+            max_size: 1024 * 1024,
+            transfer: TransferOptions {
+                include: vec![],
+                exclude: vec![],
+                concurrency: 4,
+            },
+        };
+
+        let mut client = MockClient::new();
+        client.expect_list_blobs().times(0);
+
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::empty(),
+            &mut io::sink(),
+        );
+        assert!(result.is_err(), "expected error, got ok");
     }
 
     #[test]
@@ -576,6 +3568,14 @@ mod job_tests {
         let cmd = Blob::Download {
             src: "file.txt".to_string(),
             dst: None,
+            recursive: false,
+            extract: false,
+            max_size: 1024 * 1024,
+            transfer: TransferOptions {
+                include: vec![],
+                exclude: vec![],
+                concurrency: 4,
+            },
         };
         let mut client = MockClient::new();
         client
@@ -584,7 +3584,435 @@ mod job_tests {
             .times(1)
             .returning(|_| Err(ClientError::NotFound));
 
-        let result = cmd.run(client);
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::empty(),
+            &mut io::sink(),
+        );
+        assert!(result.is_err(), "expected error, got ok");
+    }
+
+    #[test]
+    fn test_blob_stat_not_found() {
+        let cmd = Blob::Stat {
+            path: "missing.txt".to_string(),
+        };
+        let mut client = MockClient::new();
+        client
+            .expect_get_blob_metadata()
+            .with(eq("missing.txt"))
+            .times(1)
+            .returning(|_| Err(ClientError::NotFound));
+
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::empty(),
+            &mut io::sink(),
+        );
+        assert_eq!(result, Err("Blob 'missing.txt' does not exist".to_string()));
+    }
+
+    #[test]
+    fn test_runner_list() {
+        let runner = |name: &str, online: bool| RunnerInfo {
+            id: Uuid::now_v7(),
+            name: name.to_string(),
+            labels: vec!["linux".to_string()],
+            version: "0.6.0".to_string(),
+            online,
+            last_heartbeat_at: Some("2026-01-01T00:00:00Z".to_string()),
+            current_job_id: None,
+        };
+        let runners = vec![runner("up", true), runner("down", false)];
+
+        for (offline, expected) in [(false, vec!["up", "down"]), (true, vec!["down"])] {
+            let runners = runners.clone();
+            let mut client = MockClient::new();
+            client
+                .expect_list_runners()
+                .times(1)
+                .returning(move || Ok(runners.clone()));
+
+            let mut stdout = Vec::new();
+            let result = Runner::List { offline }.run(client, &mut stdout);
+            assert!(result.is_ok(), "expected ok, got {result:?}");
+            let names = String::from_utf8_lossy(&stdout)
+                .lines()
+                .map(|line| line.split('\t').nth(1).unwrap_or_default().to_string())
+                .collect::<Vec<_>>();
+            assert_eq!(names, expected, "unexpected runners with offline {offline}");
+        }
+    }
+
+    #[test]
+    fn test_runner_get_not_found() {
+        let runner_id = Uuid::now_v7();
+        let mut client = MockClient::new();
+        client
+            .expect_get_runner()
+            .with(eq(runner_id))
+            .times(1)
+            .returning(|_| Err(ClientError::NotFound));
+
+        let result = Runner::Get { runner_id }.run(client, &mut io::sink());
+        assert_eq!(result, Err(format!("Runner '{runner_id}' does not exist")));
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Ok(90));
+        assert_eq!(parse_duration("30s"), Ok(30));
+        assert_eq!(parse_duration("15m"), Ok(900));
+        assert_eq!(parse_duration("2h"), Ok(7200));
+        assert_eq!(parse_duration("7d"), Ok(604800));
+        assert!(parse_duration("0").is_err());
+        assert!(parse_duration("1w").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("").is_err());
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("500K"), Ok(512_000));
+        assert_eq!(parse_size("10M"), Ok(10 * 1024 * 1024));
+        assert_eq!(parse_size("1g"), Ok(1024 * 1024 * 1024));
+        assert!(parse_size("0").is_err());
+        assert!(parse_size("10T").is_err());
+        assert!(parse_size("M").is_err());
+    }
+
+    #[test]
+    fn test_blob_url() {
+        let cmd = Blob::Url {
+            path: "results/hosts.txt".to_string(),
+            expires_in: Some(3600),
+        };
+        let mut client = MockClient::new();
+        client
+            .expect_get_blob_url()
+            .with(eq("results/hosts.txt"), eq(Some(3600)))
+            .times(1)
+            .returning(|_, _| Ok("https://storage.example.com/signed".to_string()));
+
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::empty(),
+            &mut io::sink(),
+        );
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+    }
+
+    #[test]
+    fn test_download_blob_traversal() {
+        let root = env::temp_dir().join(Uuid::now_v7().to_string());
+        fs::create_dir_all(&root).expect("failed to create dir");
+
+        let cmd = Blob::Download {
+            src: "../escape.txt".to_string(),
+            dst: Some(root.to_string_lossy().to_string()),
+            recursive: false,
+            extract: false,
+            max_size: 1024 * 1024,
+            transfer: TransferOptions {
+                include: vec![],
+                exclude: vec![],
+                concurrency: 4,
+            },
+        };
+        let mut client = MockClient::new();
+        client.expect_download_blob_file().times(0);
+
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::empty(),
+            &mut io::sink(),
+        );
+        fs::remove_dir_all(&root).ok();
+        assert!(result.is_err(), "expected error, got ok");
+    }
+
+    #[test]
+    fn test_download_blob_extract() {
+        let root = env::temp_dir().join(Uuid::now_v7().to_string());
+
+        let mut builder = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(5);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "results/hosts.txt", &b"hello"[..])
+            .expect("failed to append to tar");
+        let archive = builder.into_inner().expect("failed to build tar");
+
+        let cmd = Blob::Download {
+            src: "scans/results.tar".to_string(),
+            dst: Some(root.to_string_lossy().to_string()),
+            recursive: false,
+            extract: true,
+            max_size: 1024 * 1024,
+            transfer: TransferOptions {
+                include: vec![],
+                exclude: vec![],
+                concurrency: 4,
+            },
+        };
+        let mut client = MockClient::new();
+        client
+            .expect_download_blob_file()
+            .with(eq("scans/results.tar"))
+            .times(1)
+            .returning(move |_| Ok((Box::new(io::Cursor::new(archive.clone())), None)));
+
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::empty(),
+            &mut io::sink(),
+        );
+        let content = fs::read_to_string(root.join("results/hosts.txt"));
+        fs::remove_dir_all(&root).ok();
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+        assert_eq!(content.ok().as_deref(), Some("hello"));
+    }
+
+    #[test]
+    fn test_upload_blob_options() {
+        let path = env::temp_dir().join(Uuid::now_v7().to_string());
+        fs::write(&path, "hello").expect("failed to write file");
+        let hello = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+        let cmd = Blob::Upload {
+            src: path.to_string_lossy().to_string(),
+            dst: "hello.txt".to_string(),
+            compress: Some(ContentEncoding::Zstd),
+            encrypt: true,
+            transfer: TransferOptions {
+                include: vec![],
+                exclude: vec![],
+                concurrency: 4,
+            },
+        };
+        let mut client = MockClient::new();
+        client.expect_encrypt_blob().times(1).returning(Ok);
+        client
+            .expect_upload_blob_file()
+            .with(
+                always(),
+                always(),
+                eq("hello.txt"),
+                eq(UploadOptions {
+                    encoding: Some(ContentEncoding::Zstd),
+                    encrypt: true,
+                }),
+                eq(Some(hello.to_string())),
+            )
+            .times(1)
+            .returning(|mut reader, size, _, _, _| {
+                let mut compressed = Vec::new();
+                reader
+                    .read_to_end(&mut compressed)
+                    .expect("content to read");
+                assert_eq!(size, compressed.len() as u64);
+
+                let mut content = String::new();
+                ContentEncoding::Zstd
+                    .decoder(Box::new(io::Cursor::new(compressed)))
+                    .and_then(|mut r| r.read_to_string(&mut content))
+                    .expect("content to decompress");
+                assert_eq!(content, "hello");
+                Ok(())
+            });
+
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::empty(),
+            &mut io::sink(),
+        );
+        fs::remove_file(&path).ok();
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+    }
+
+    #[test]
+    fn test_upload_blob_encrypted() {
+        let path = env::temp_dir().join(Uuid::now_v7().to_string());
+        fs::write(&path, "hello").expect("failed to write file");
+        let hello = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+        let cmd = Blob::Upload {
+            src: path.to_string_lossy().to_string(),
+            dst: "hello.txt".to_string(),
+            compress: None,
+            encrypt: true,
+            transfer: TransferOptions {
+                include: vec![],
+                exclude: vec![],
+                concurrency: 4,
+            },
+        };
+        let mut client = MockClient::new();
+        client
+            .expect_encrypt_blob()
+            .times(1)
+            .returning(|mut reader| {
+                let mut content = Vec::new();
+                reader.read_to_end(&mut content).expect("content to read");
+                content.reverse();
+                Ok(Box::new(io::Cursor::new(content)))
+            });
+        client
+            .expect_upload_blob_file()
+            .with(
+                always(),
+                eq(5),
+                eq("hello.txt"),
+                eq(UploadOptions {
+                    encoding: None,
+                    encrypt: true,
+                }),
+                eq(Some(hello.to_string())),
+            )
+            .times(1)
+            .returning(|mut reader, _, _, _, _| {
+                let mut content = String::new();
+                reader
+                    .read_to_string(&mut content)
+                    .expect("content to read");
+                assert_eq!(content, "olleh");
+                Ok(())
+            });
+
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::empty(),
+            &mut io::sink(),
+        );
+        fs::remove_file(&path).ok();
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+    }
+
+    #[test]
+    fn test_upload_blob_from_stdin() {
+        let cmd = Blob::Upload {
+            src: "-".to_string(),
+            dst: "hello.txt".to_string(),
+            compress: None,
+            encrypt: false,
+            transfer: TransferOptions {
+                include: vec![],
+                exclude: vec![],
+                concurrency: 4,
+            },
+        };
+        let mut client = MockClient::new();
+        client
+            .expect_upload_blob_file()
+            .with(
+                always(),
+                eq(5),
+                eq("hello.txt"),
+                eq(UploadOptions::default()),
+                eq(None),
+            )
+            .times(1)
+            .returning(|mut reader, _, _, _, _| {
+                let mut content = String::new();
+                reader
+                    .read_to_string(&mut content)
+                    .expect("content to read");
+                assert_eq!(content, "hello");
+                Ok(())
+            });
+
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::Cursor::new("hello"),
+            &mut io::sink(),
+        );
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+    }
+
+    #[test]
+    fn test_upload_blob_from_stdin_compressed() {
+        let hello = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        let cmd = Blob::Upload {
+            src: "-".to_string(),
+            dst: "hello.txt".to_string(),
+            compress: Some(ContentEncoding::Gzip),
+            encrypt: false,
+            transfer: TransferOptions {
+                include: vec![],
+                exclude: vec![],
+                concurrency: 4,
+            },
+        };
+        let mut client = MockClient::new();
+        client
+            .expect_upload_blob_file()
+            .with(
+                always(),
+                always(),
+                eq("hello.txt"),
+                eq(UploadOptions {
+                    encoding: Some(ContentEncoding::Gzip),
+                    encrypt: false,
+                }),
+                eq(Some(hello.to_string())),
+            )
+            .times(1)
+            .returning(|mut reader, _, _, _, _| {
+                let mut compressed = Vec::new();
+                reader
+                    .read_to_end(&mut compressed)
+                    .expect("content to read");
+
+                let mut content = String::new();
+                ContentEncoding::Gzip
+                    .decoder(Box::new(io::Cursor::new(compressed)))
+                    .and_then(|mut r| r.read_to_string(&mut content))
+                    .expect("content to decompress");
+                assert_eq!(content, "hello");
+                Ok(())
+            });
+
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::Cursor::new("hello"),
+            &mut io::sink(),
+        );
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+    }
+
+    #[test]
+    fn test_upload_blob_invalid_path() {
+        let cmd = Blob::Upload {
+            src: "-".to_string(),
+            dst: "/etc/passwd".to_string(),
+            compress: None,
+            encrypt: false,
+            transfer: TransferOptions {
+                include: vec![],
+                exclude: vec![],
+                concurrency: 4,
+            },
+        };
+        let mut client = MockClient::new();
+        client.expect_upload_blob_file().times(0);
+
+        let result = cmd.run(
+            client,
+            &Progress::hidden(),
+            &mut io::empty(),
+            &mut io::sink(),
+        );
         assert!(result.is_err(), "expected error, got ok");
     }
 }
@@ -623,6 +4051,27 @@ impl Bhlast {
     }
 }
 
+#[derive(Subcommand, Debug)]
+enum Cache {
+    /// Remove every cached download
+    Clean,
+}
+
+impl Cache {
+    fn run(&self) -> Result<()> {
+        match self {
+            Cache::Clean => {
+                let cache = download_cache(&Config::load()?)?.ok_or_else(|| {
+                    "Failed to find a cache directory, set XDG_CACHE_HOME".to_string()
+                })?;
+                let freed = cache.clean()?;
+                println!("Removed {} of cached downloads", HumanBytes(freed));
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Subcommand, Debug)]
 enum Md {
     #[command(about = "Generate markdown documentation for the CLI")]
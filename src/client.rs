@@ -1,13 +1,17 @@
+use crate::compression::ContentEncoding;
+use crate::download_cache::DownloadCache;
+use crate::encryption::Identity;
+use crate::throttle::{RateLimiter, Throttled};
 #[cfg(test)]
 use mockall::automock;
 use serde::{Deserialize, Serialize};
 use serde_json::{Value, json};
 use std::collections::BTreeMap;
-use std::fs::File;
 use std::io::Read;
+use std::path::PathBuf;
 use std::time::Duration;
-use ureq::Agent;
 use ureq::tls::{RootCerts, TlsConfig};
+use ureq::{Agent, SendBody};
 use uuid::Uuid;
 
 use thiserror::Error;
@@ -51,6 +55,154 @@ pub struct DispatchScanRequest {
 #[serde(rename_all = "camelCase")]
 pub struct UploadBlobFileRequest {
     pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_encoding: Option<ContentEncoding>,
+    pub encrypted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plaintext_hash: Option<String>,
+}
+
+/// Transformations applied to blob content on the client before it is sent.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UploadOptions {
+    /// Compress the content with the encoding
+    pub encoding: Option<ContentEncoding>,
+    /// Encrypt the content to the identity set with `BOUNTYHUB_IDENTITY_FILE`
+    pub encrypt: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWorkflowRequest {
+    pub name: String,
+    pub source: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWorkflowRevisionRequest {
+    pub source: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkflowRevisionResponse {
+    pub workflow_id: Uuid,
+    pub revision_id: Uuid,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkflowRevision {
+    pub id: Uuid,
+    pub created_at: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct JobInfo {
+    pub id: Uuid,
+    pub scan_name: String,
+    pub state: String,
+    pub created_at: String,
+    /// Names of the artifacts the job uploaded
+    #[serde(default)]
+    pub artifacts: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WorkflowVar {
+    pub key: String,
+    pub value: String,
+}
+
+/// Secrets are write-only, so the API never returns their values.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkflowSecret {
+    pub key: String,
+    pub updated_at: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SetValueRequest {
+    pub value: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobInfo {
+    pub path: String,
+    pub size: u64,
+    pub modified_at: String,
+    /// Hex encoded SHA-256 of the blob content
+    pub hash: String,
+    /// Hex encoded SHA-256 of the content before it was compressed or
+    /// encrypted, for blobs uploaded with either
+    #[serde(default)]
+    pub plaintext_hash: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobMetadata {
+    pub path: String,
+    pub size: u64,
+    pub content_type: String,
+    /// Set when the content was compressed by the CLI on upload
+    #[serde(default)]
+    pub content_encoding: Option<ContentEncoding>,
+    /// Set when the content was encrypted by the CLI on upload
+    #[serde(default)]
+    pub encrypted: bool,
+    /// Hex encoded SHA-256 of the blob content
+    pub hash: String,
+    pub uploaded_at: String,
+    pub uploaded_by: String,
+}
+
+/// One page of a blob listing.
+///
+/// Unless the listing is recursive, blobs nested deeper than the prefix are
+/// grouped under their directory in `prefixes`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobPage {
+    pub blobs: Vec<BlobInfo>,
+    #[serde(default)]
+    pub prefixes: Vec<String>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TransferBlobRequest {
+    pub src: String,
+    pub dst: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMultipartUploadRequest {
+    pub path: String,
+    pub size: u64,
+    pub part_size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_encoding: Option<ContentEncoding>,
+    pub encrypted: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plaintext_hash: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CompletedPart {
+    pub part_number: u32,
+    pub etag: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CompleteMultipartUploadRequest {
+    pub parts: Vec<CompletedPart>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -59,18 +211,52 @@ pub struct RunnerRegistrationResponse {
     pub token: String,
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RunnerInfo {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    pub version: String,
+    pub online: bool,
+    /// Unset when the runner never connected
+    #[serde(default)]
+    pub last_heartbeat_at: Option<String>,
+    /// Job the runner is executing, if any
+    #[serde(default)]
+    pub current_job_id: Option<Uuid>,
+}
+
 #[cfg_attr(test, automock)]
 pub trait Client {
+    /// Downloads the artifact content, along with its size when the server sent it.
     fn download_job_artifact(
         &self,
         job_id: Uuid,
         name: &str,
-    ) -> Result<Box<dyn Read + Send + Sync + 'static>>;
+    ) -> Result<(Box<dyn Read + Send + Sync + 'static>, Option<u64>)>;
+
+    /// Returns the presigned URL the artifact can be downloaded from without a token.
+    fn get_job_artifact_url(
+        &self,
+        job_id: Uuid,
+        name: &str,
+        expires_in: Option<u64>,
+    ) -> Result<String>;
 
     fn delete_job_artifact(&self, job_id: Uuid, name: &str) -> Result<()>;
 
     fn delete_job(&self, job_id: Uuid) -> Result<()>;
 
+    /// Lists jobs of the scan, optionally only the ones in the given state.
+    fn list_scan_jobs(
+        &self,
+        workflow_id: Uuid,
+        scan_name: &str,
+        state: Option<String>,
+    ) -> Result<Vec<JobInfo>>;
+
     fn dispatch_scan(
         &self,
         workflow_id: Uuid,
@@ -78,12 +264,122 @@ pub trait Client {
         inputs: Option<BTreeMap<String, Value>>,
     ) -> Result<()>;
 
-    fn download_blob_file(&self, path: &str) -> Result<Box<dyn Read + Send + Sync + 'static>>;
+    fn create_workflow(&self, name: String, source: String) -> Result<WorkflowRevisionResponse>;
+
+    fn create_workflow_revision(
+        &self,
+        workflow_id: Uuid,
+        source: String,
+    ) -> Result<WorkflowRevisionResponse>;
+
+    fn list_workflow_revisions(&self, workflow_id: Uuid) -> Result<Vec<WorkflowRevision>>;
+
+    fn get_workflow_revision_source(&self, workflow_id: Uuid, revision_id: Uuid) -> Result<String>;
+
+    fn list_workflow_vars(&self, workflow_id: Uuid) -> Result<Vec<WorkflowVar>>;
+
+    fn set_workflow_var(&self, workflow_id: Uuid, key: &str, value: String) -> Result<()>;
+
+    fn delete_workflow_var(&self, workflow_id: Uuid, key: &str) -> Result<()>;
+
+    fn list_workflow_secrets(&self, workflow_id: Uuid) -> Result<Vec<WorkflowSecret>>;
+
+    fn set_workflow_secret(&self, workflow_id: Uuid, key: &str, value: String) -> Result<()>;
+
+    fn delete_workflow_secret(&self, workflow_id: Uuid, key: &str) -> Result<()>;
+
+    fn list_blobs(&self, prefix: &str, recursive: bool, cursor: Option<String>)
+    -> Result<BlobPage>;
+
+    /// Downloads the blob content, decrypting and decompressing it if it was
+    /// uploaded with encryption or an encoding.
+    ///
+    /// The size is returned when the server sent it and the content is not decoded,
+    /// as the decoded size is not known upfront.
+    fn download_blob_file(
+        &self,
+        path: &str,
+    ) -> Result<(Box<dyn Read + Send + Sync + 'static>, Option<u64>)>;
+
+    fn get_blob_metadata(&self, path: &str) -> Result<BlobMetadata>;
+
+    /// Returns the presigned URL the blob can be downloaded from without a token.
+    ///
+    /// The URL serves the content as stored, so compressed or encrypted blobs are
+    /// not decoded.
+    fn get_blob_url(&self, path: &str, expires_in: Option<u64>) -> Result<String>;
+
+    /// Streams `len` bytes starting at `offset` from a presigned URL with a range request.
+    fn download_url_range(
+        &self,
+        url: &str,
+        offset: u64,
+        len: u64,
+    ) -> Result<Box<dyn Read + Send + Sync + 'static>>;
+
+    /// Reads up to the last `len` bytes from a presigned URL with a range
+    /// request, returning them along with the total size of the content.
+    fn download_url_tail(&self, url: &str, len: u64) -> Result<(Vec<u8>, u64)>;
+
+    /// Wraps the reader so that reading from it yields the content encrypted to
+    /// the identity set with `BOUNTYHUB_IDENTITY_FILE`.
+    fn encrypt_blob(
+        &self,
+        reader: Box<dyn Read + Send + 'static>,
+    ) -> Result<Box<dyn Read + Send + 'static>>;
+
+    /// Uploads `size` bytes of the reader content to the blob path.
+    ///
+    /// The content is sent as is, the options only record how it was encoded
+    /// in the blob metadata, so downloads can reverse them. Encoded content
+    /// comes with the hash of the content it was encoded from.
+    fn upload_blob_file(
+        &self,
+        reader: Box<dyn Read + Send + 'static>,
+        size: u64,
+        dst: &str,
+        options: UploadOptions,
+        plaintext_hash: Option<String>,
+    ) -> Result<()>;
+
+    /// Starts a multipart upload to the blob path, returning the upload ID.
+    ///
+    /// The parts are sent as is, the options and plaintext hash only record
+    /// how they were encoded.
+    fn create_multipart_upload(
+        &self,
+        dst: &str,
+        size: u64,
+        part_size: u64,
+        options: UploadOptions,
+        plaintext_hash: Option<String>,
+    ) -> Result<String>;
+
+    /// Uploads one part of a multipart upload, returning the ETag of the part.
+    ///
+    /// Part numbers start at 1.
+    fn upload_blob_part(
+        &self,
+        upload_id: &str,
+        part_number: u32,
+        reader: Box<dyn Read + Send + 'static>,
+        size: u64,
+    ) -> Result<String>;
+
+    fn complete_multipart_upload(&self, upload_id: &str, parts: Vec<CompletedPart>) -> Result<()>;
+
+    fn delete_blob(&self, path: &str) -> Result<()>;
 
-    fn upload_blob_file(&self, file: File, dst: &str) -> Result<()>;
+    fn move_blob(&self, src: &str, dst: &str) -> Result<()>;
+
+    fn copy_blob(&self, src: &str, dst: &str) -> Result<()>;
 
     fn create_runner_registration(&self) -> Result<RunnerRegistrationResponse>;
 
+    fn list_runners(&self) -> Result<Vec<RunnerInfo>>;
+
+    fn get_runner(&self, runner_id: Uuid) -> Result<RunnerInfo>;
+
     fn create_bhlast_domain(&self) -> Result<String>;
 }
 
@@ -92,6 +388,10 @@ pub struct HTTPClient {
     bountyhub_domain: String,
     bountyhub_agent: Agent,
     file_agent: Agent,
+    identity_file: Option<PathBuf>,
+    /// Shared by every file transfer of the client
+    limiter: Option<RateLimiter>,
+    download_cache: Option<DownloadCache>,
 }
 
 impl HTTPClient {
@@ -127,9 +427,88 @@ impl HTTPClient {
             bountyhub_domain: bountyhub_domain.to_string(),
             bountyhub_agent,
             file_agent,
+            identity_file: None,
+            limiter: None,
+            download_cache: None,
+        }
+    }
+
+    /// Sets the file holding the identity blobs are encrypted to on upload and
+    /// decrypted with on download.
+    ///
+    /// The file is only read when a blob is encrypted or decrypted, so commands
+    /// that do neither work without it.
+    pub fn with_identity_file(mut self, path: PathBuf) -> Self {
+        self.identity_file = Some(path);
+        self
+    }
+
+    /// Limits all file transfers together to `bytes_per_sec`.
+    pub fn with_rate_limit(mut self, bytes_per_sec: u64) -> Self {
+        self.limiter = Some(RateLimiter::new(bytes_per_sec));
+        self
+    }
+
+    /// Keeps downloaded blobs and artifacts in the cache, downloading them
+    /// again only when their ETag changed.
+    pub fn with_download_cache(mut self, cache: DownloadCache) -> Self {
+        self.download_cache = Some(cache);
+        self
+    }
+
+    /// Downloads the file behind a presigned URL, along with its size when known.
+    ///
+    /// With a download cache, the request is conditional on the ETag of the
+    /// cached copy of `key`, which is reused when the file did not change.
+    fn download_file(
+        &self,
+        key: &str,
+        url: &str,
+    ) -> Result<(Box<dyn Read + Send + Sync + 'static>, Option<u64>)> {
+        let key = format!("{}/{key}", self.bountyhub_domain);
+        let cache = self.download_cache.as_ref();
+
+        let mut req = self.file_agent.get(url);
+        if let Some(etag) = cache.and_then(|cache| cache.etag(&key)) {
+            req = req.header("If-None-Match", etag.as_str());
+        }
+        let mut res = req.call()?;
+        if res.status().as_u16() == 304 {
+            match cache.map(|cache| cache.open(&key)) {
+                Some(Ok(file)) => {
+                    let size = file.metadata().ok().map(|metadata| metadata.len());
+                    return Ok((Box::new(file), size));
+                }
+                // The cached copy went away in the meantime
+                _ => res = self.file_agent.get(url).call()?,
+            }
+        }
+
+        let size = res.body().content_length();
+        let etag = res
+            .headers()
+            .get("etag")
+            .and_then(|etag| etag.to_str().ok())
+            .map(str::to_string);
+        let reader = self.throttle(res.into_body().into_reader());
+        match (cache, etag) {
+            (Some(cache), Some(etag)) => Ok((Box::new(cache.fill(&key, &etag, reader)), size)),
+            _ => Ok((Box::new(reader), size)),
         }
     }
 
+    /// Wraps the reader of file content sent or received, to keep it under the rate limit.
+    fn throttle<R: Read>(&self, reader: R) -> Throttled<R> {
+        Throttled::new(reader, self.limiter.clone())
+    }
+
+    fn identity(&self) -> Result<Identity> {
+        let path = self.identity_file.as_ref().ok_or_else(|| {
+            Error::Generic("Encryption requires BOUNTYHUB_IDENTITY_FILE to be set".to_string())
+        })?;
+        Identity::load(path).map_err(Error::Generic)
+    }
+
     #[cfg(test)]
     pub fn bountyhub_domain(&self) -> String {
         self.bountyhub_domain.clone()
@@ -146,7 +525,7 @@ impl Client for HTTPClient {
         &self,
         job_id: Uuid,
         name: &str,
-    ) -> Result<Box<dyn Read + Send + Sync + 'static>> {
+    ) -> Result<(Box<dyn Read + Send + Sync + 'static>, Option<u64>)> {
         let url = format!(
             "{0}/api/v0/workflows/jobs/{job_id}/artifacts/{name}",
             self.bountyhub_domain
@@ -159,9 +538,30 @@ impl Client for HTTPClient {
             .body_mut()
             .read_json()?;
 
-        let res = self.file_agent.get(url.as_str()).call()?;
+        self.download_file(&format!("artifacts/{job_id}/{name}"), &url)
+    }
+
+    fn get_job_artifact_url(
+        &self,
+        job_id: Uuid,
+        name: &str,
+        expires_in: Option<u64>,
+    ) -> Result<String> {
+        let url = format!(
+            "{0}/api/v0/workflows/jobs/{job_id}/artifacts/{name}",
+            self.bountyhub_domain
+        );
+
+        let mut req = self
+            .bountyhub_agent
+            .get(url.as_str())
+            .header("Authorization", self.authorization.as_str());
+        if let Some(expires_in) = expires_in {
+            req = req.query("expiresIn", expires_in.to_string());
+        }
+        let UrlResponse { url } = req.call()?.body_mut().read_json()?;
 
-        Ok(Box::new(res.into_body().into_reader()))
+        Ok(url)
     }
 
     fn delete_job_artifact(&self, job_id: Uuid, name: &str) -> Result<()> {
@@ -188,6 +588,29 @@ impl Client for HTTPClient {
         Ok(())
     }
 
+    fn list_scan_jobs(
+        &self,
+        workflow_id: Uuid,
+        scan_name: &str,
+        state: Option<String>,
+    ) -> Result<Vec<JobInfo>> {
+        let url = format!(
+            "{0}/api/v0/workflows/{workflow_id}/scans/{1}/jobs",
+            self.bountyhub_domain,
+            encode(scan_name)
+        );
+
+        let mut req = self
+            .bountyhub_agent
+            .get(url.as_str())
+            .header("Authorization", self.authorization.as_str());
+        if let Some(state) = state {
+            req = req.query("state", state);
+        }
+
+        Ok(req.call()?.body_mut().read_json()?)
+    }
+
     fn dispatch_scan(
         &self,
         workflow_id: Uuid,
@@ -207,22 +630,239 @@ impl Client for HTTPClient {
         Ok(())
     }
 
-    fn download_blob_file(&self, path: &str) -> Result<Box<dyn Read + Send + Sync + 'static>> {
+    fn create_workflow(&self, name: String, source: String) -> Result<WorkflowRevisionResponse> {
+        let url = format!("{0}/api/v0/workflows", self.bountyhub_domain);
+
+        Ok(self
+            .bountyhub_agent
+            .post(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .send_json(CreateWorkflowRequest { name, source })?
+            .body_mut()
+            .read_json()?)
+    }
+
+    fn create_workflow_revision(
+        &self,
+        workflow_id: Uuid,
+        source: String,
+    ) -> Result<WorkflowRevisionResponse> {
+        let url = format!(
+            "{0}/api/v0/workflows/{workflow_id}/revisions",
+            self.bountyhub_domain
+        );
+
+        Ok(self
+            .bountyhub_agent
+            .post(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .send_json(CreateWorkflowRevisionRequest { source })?
+            .body_mut()
+            .read_json()?)
+    }
+
+    fn list_workflow_revisions(&self, workflow_id: Uuid) -> Result<Vec<WorkflowRevision>> {
+        let url = format!(
+            "{0}/api/v0/workflows/{workflow_id}/revisions",
+            self.bountyhub_domain
+        );
+
+        Ok(self
+            .bountyhub_agent
+            .get(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .call()?
+            .body_mut()
+            .read_json()?)
+    }
+
+    fn get_workflow_revision_source(&self, workflow_id: Uuid, revision_id: Uuid) -> Result<String> {
+        let url = format!(
+            "{0}/api/v0/workflows/{workflow_id}/revisions/{revision_id}",
+            self.bountyhub_domain
+        );
+
+        let SourceResponse { source } = self
+            .bountyhub_agent
+            .get(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .call()?
+            .body_mut()
+            .read_json()?;
+
+        Ok(source)
+    }
+
+    fn list_workflow_vars(&self, workflow_id: Uuid) -> Result<Vec<WorkflowVar>> {
+        let url = format!(
+            "{0}/api/v0/workflows/{workflow_id}/vars",
+            self.bountyhub_domain
+        );
+
+        Ok(self
+            .bountyhub_agent
+            .get(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .call()?
+            .body_mut()
+            .read_json()?)
+    }
+
+    fn set_workflow_var(&self, workflow_id: Uuid, key: &str, value: String) -> Result<()> {
+        let url = format!(
+            "{0}/api/v0/workflows/{workflow_id}/vars/{1}",
+            self.bountyhub_domain,
+            encode(key),
+        );
+
+        self.bountyhub_agent
+            .put(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .send_json(SetValueRequest { value })?;
+
+        Ok(())
+    }
+
+    fn delete_workflow_var(&self, workflow_id: Uuid, key: &str) -> Result<()> {
+        let url = format!(
+            "{0}/api/v0/workflows/{workflow_id}/vars/{1}",
+            self.bountyhub_domain,
+            encode(key),
+        );
+
+        self.bountyhub_agent
+            .delete(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .call()?;
+
+        Ok(())
+    }
+
+    fn list_workflow_secrets(&self, workflow_id: Uuid) -> Result<Vec<WorkflowSecret>> {
+        let url = format!(
+            "{0}/api/v0/workflows/{workflow_id}/secrets",
+            self.bountyhub_domain
+        );
+
+        Ok(self
+            .bountyhub_agent
+            .get(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .call()?
+            .body_mut()
+            .read_json()?)
+    }
+
+    fn set_workflow_secret(&self, workflow_id: Uuid, key: &str, value: String) -> Result<()> {
+        let url = format!(
+            "{0}/api/v0/workflows/{workflow_id}/secrets/{1}",
+            self.bountyhub_domain,
+            encode(key),
+        );
+
+        self.bountyhub_agent
+            .put(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .send_json(SetValueRequest { value })?;
+
+        Ok(())
+    }
+
+    fn delete_workflow_secret(&self, workflow_id: Uuid, key: &str) -> Result<()> {
+        let url = format!(
+            "{0}/api/v0/workflows/{workflow_id}/secrets/{1}",
+            self.bountyhub_domain,
+            encode(key),
+        );
+
+        self.bountyhub_agent
+            .delete(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .call()?;
+
+        Ok(())
+    }
+
+    fn list_blobs(
+        &self,
+        prefix: &str,
+        recursive: bool,
+        cursor: Option<String>,
+    ) -> Result<BlobPage> {
+        let url = format!("{0}/api/v0/blobs", self.bountyhub_domain);
+
+        let mut req = self
+            .bountyhub_agent
+            .get(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .query("prefix", prefix)
+            .query("recursive", recursive.to_string());
+        if let Some(cursor) = cursor {
+            req = req.query("cursor", cursor);
+        }
+
+        Ok(req.call()?.body_mut().read_json()?)
+    }
+
+    fn download_blob_file(
+        &self,
+        path: &str,
+    ) -> Result<(Box<dyn Read + Send + Sync + 'static>, Option<u64>)> {
         let url = format!("{0}/api/v0/blobs/{1}", self.bountyhub_domain, encode(path),);
-        let UrlResponse { url } = self
+        let BlobUrlResponse {
+            url,
+            content_encoding,
+            encrypted,
+        } = self
             .bountyhub_agent
             .get(url.as_str())
             .header("Authorization", self.authorization.as_str())
             .call()?
             .body_mut()
             .read_json()?;
+        let identity = if encrypted {
+            Some(self.identity()?)
+        } else {
+            None
+        };
 
-        let res = self.file_agent.get(url.as_str()).call()?;
+        let (mut reader, size) = self.download_file(&format!("blobs/{path}"), &url)?;
+        let size = match (encrypted, content_encoding) {
+            (false, None) => size,
+            _ => None,
+        };
+
+        if let Some(identity) = identity {
+            reader = identity
+                .decryptor(reader)
+                .map_err(|err| Error::Generic(format!("Failed to decrypt: {err:?}")))?;
+        }
+        if let Some(encoding) = content_encoding {
+            reader = encoding
+                .decoder(reader)
+                .map_err(|err| Error::Generic(format!("Failed to decode {encoding}: {err:?}")))?;
+        }
 
-        Ok(Box::new(res.into_body().into_reader()))
+        Ok((reader, size))
     }
 
-    fn upload_blob_file(&self, file: File, dst: &str) -> Result<()> {
+    fn encrypt_blob(
+        &self,
+        reader: Box<dyn Read + Send + 'static>,
+    ) -> Result<Box<dyn Read + Send + 'static>> {
+        self.identity()?
+            .encryptor(reader)
+            .map_err(|err| Error::Generic(format!("Failed to encrypt: {err:?}")))
+    }
+
+    fn upload_blob_file(
+        &self,
+        reader: Box<dyn Read + Send + 'static>,
+        size: u64,
+        dst: &str,
+        options: UploadOptions,
+        plaintext_hash: Option<String>,
+    ) -> Result<()> {
         let url = format!("{0}/api/v0/blobs/files", self.bountyhub_domain);
         let UrlResponse { url } = self
             .bountyhub_agent
@@ -230,11 +870,222 @@ impl Client for HTTPClient {
             .header("Authorization", self.authorization.as_str())
             .send_json(UploadBlobFileRequest {
                 path: dst.to_string(),
+                content_encoding: options.encoding,
+                encrypted: options.encrypt,
+                plaintext_hash,
+            })?
+            .body_mut()
+            .read_json()?;
+
+        self.file_agent
+            .put(&url)
+            .header("Content-Length", size.to_string())
+            .send(SendBody::from_owned_reader(self.throttle(reader)))?;
+
+        Ok(())
+    }
+
+    fn get_blob_metadata(&self, path: &str) -> Result<BlobMetadata> {
+        let url = format!(
+            "{0}/api/v0/blobs/{1}/metadata",
+            self.bountyhub_domain,
+            encode(path)
+        );
+
+        Ok(self
+            .bountyhub_agent
+            .get(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .call()?
+            .body_mut()
+            .read_json()?)
+    }
+
+    fn get_blob_url(&self, path: &str, expires_in: Option<u64>) -> Result<String> {
+        let url = format!("{0}/api/v0/blobs/{1}", self.bountyhub_domain, encode(path));
+
+        let mut req = self
+            .bountyhub_agent
+            .get(url.as_str())
+            .header("Authorization", self.authorization.as_str());
+        if let Some(expires_in) = expires_in {
+            req = req.query("expiresIn", expires_in.to_string());
+        }
+        let BlobUrlResponse { url, .. } = req.call()?.body_mut().read_json()?;
+
+        Ok(url)
+    }
+
+    fn download_url_range(
+        &self,
+        url: &str,
+        offset: u64,
+        len: u64,
+    ) -> Result<Box<dyn Read + Send + Sync + 'static>> {
+        if len == 0 {
+            return Ok(Box::new(std::io::empty()));
+        }
+
+        let res = self
+            .file_agent
+            .get(url)
+            .header("Range", format!("bytes={offset}-{}", offset + len - 1))
+            .call()?;
+        if res.status() != 206 {
+            return Err(Error::Generic(
+                "Server does not support range requests".to_string(),
+            ));
+        }
+
+        Ok(Box::new(
+            self.throttle(res.into_body().into_reader().take(len)),
+        ))
+    }
+
+    fn download_url_tail(&self, url: &str, len: u64) -> Result<(Vec<u8>, u64)> {
+        let mut res = self
+            .file_agent
+            .get(url)
+            .header("Range", format!("bytes=-{len}"))
+            .call()?;
+        if res.status() != 206 {
+            return Err(Error::Generic(
+                "Server does not support range requests".to_string(),
+            ));
+        }
+
+        // Content-Range: bytes <first>-<last>/<total>
+        let total = res
+            .headers()
+            .get("Content-Range")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.rsplit_once('/'))
+            .and_then(|(_, total)| total.parse::<u64>().ok())
+            .ok_or_else(|| Error::Generic("Missing or invalid Content-Range header".to_string()))?;
+
+        let mut tail = Vec::new();
+        res.body_mut()
+            .as_reader()
+            .take(len)
+            .read_to_end(&mut tail)
+            .map_err(|err| Error::Generic(format!("Failed to read response: {err:?}")))?;
+
+        Ok((tail, total))
+    }
+
+    fn create_multipart_upload(
+        &self,
+        dst: &str,
+        size: u64,
+        part_size: u64,
+        options: UploadOptions,
+        plaintext_hash: Option<String>,
+    ) -> Result<String> {
+        let url = format!("{0}/api/v0/blobs/multipart", self.bountyhub_domain);
+
+        let MultipartUploadResponse { upload_id } = self
+            .bountyhub_agent
+            .post(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .send_json(CreateMultipartUploadRequest {
+                path: dst.to_string(),
+                size,
+                part_size,
+                content_encoding: options.encoding,
+                encrypted: options.encrypt,
+                plaintext_hash,
             })?
             .body_mut()
             .read_json()?;
 
-        self.file_agent.put(&url).send(file)?;
+        Ok(upload_id)
+    }
+
+    fn upload_blob_part(
+        &self,
+        upload_id: &str,
+        part_number: u32,
+        reader: Box<dyn Read + Send + 'static>,
+        size: u64,
+    ) -> Result<String> {
+        let url = format!(
+            "{0}/api/v0/blobs/multipart/{1}/parts/{part_number}",
+            self.bountyhub_domain,
+            encode(upload_id)
+        );
+        let UrlResponse { url } = self
+            .bountyhub_agent
+            .post(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .send_json(json!({}))?
+            .body_mut()
+            .read_json()?;
+
+        let response = self
+            .file_agent
+            .put(&url)
+            .header("Content-Length", size.to_string())
+            .send(SendBody::from_owned_reader(self.throttle(reader)))?;
+
+        let etag = response
+            .headers()
+            .get("ETag")
+            .and_then(|etag| etag.to_str().ok())
+            .ok_or_else(|| Error::Generic(format!("Part {part_number} response has no ETag")))?;
+
+        Ok(etag.to_string())
+    }
+
+    fn complete_multipart_upload(&self, upload_id: &str, parts: Vec<CompletedPart>) -> Result<()> {
+        let url = format!(
+            "{0}/api/v0/blobs/multipart/{1}/complete",
+            self.bountyhub_domain,
+            encode(upload_id)
+        );
+
+        self.bountyhub_agent
+            .post(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .send_json(CompleteMultipartUploadRequest { parts })?;
+
+        Ok(())
+    }
+
+    fn delete_blob(&self, path: &str) -> Result<()> {
+        let url = format!("{0}/api/v0/blobs/{1}", self.bountyhub_domain, encode(path));
+
+        self.bountyhub_agent
+            .delete(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .call()?;
+
+        Ok(())
+    }
+
+    fn move_blob(&self, src: &str, dst: &str) -> Result<()> {
+        let url = format!("{0}/api/v0/blobs/move", self.bountyhub_domain);
+
+        self.bountyhub_agent
+            .post(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .send_json(TransferBlobRequest {
+                src: src.to_string(),
+                dst: dst.to_string(),
+            })?;
+
+        Ok(())
+    }
+
+    fn copy_blob(&self, src: &str, dst: &str) -> Result<()> {
+        let url = format!("{0}/api/v0/blobs/copy", self.bountyhub_domain);
+
+        self.bountyhub_agent
+            .post(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .send_json(TransferBlobRequest {
+                src: src.to_string(),
+                dst: dst.to_string(),
+            })?;
 
         Ok(())
     }
@@ -251,6 +1102,30 @@ impl Client for HTTPClient {
             .read_json()?)
     }
 
+    fn list_runners(&self) -> Result<Vec<RunnerInfo>> {
+        let url = format!("{0}/api/v0/runners", self.bountyhub_domain);
+
+        Ok(self
+            .bountyhub_agent
+            .get(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .call()?
+            .body_mut()
+            .read_json()?)
+    }
+
+    fn get_runner(&self, runner_id: Uuid) -> Result<RunnerInfo> {
+        let url = format!("{0}/api/v0/runners/{runner_id}", self.bountyhub_domain);
+
+        Ok(self
+            .bountyhub_agent
+            .get(url.as_str())
+            .header("Authorization", self.authorization.as_str())
+            .call()?
+            .body_mut()
+            .read_json()?)
+    }
+
     fn create_bhlast_domain(&self) -> Result<String> {
         let url = format!("{0}/api/v0/bhlast/domains", self.bountyhub_domain);
 
@@ -275,7 +1150,28 @@ struct UrlResponse {
     url: String,
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct BlobUrlResponse {
+    url: String,
+    #[serde(default)]
+    content_encoding: Option<ContentEncoding>,
+    #[serde(default)]
+    encrypted: bool,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct MultipartUploadResponse {
+    upload_id: String,
+}
+
 #[derive(Deserialize, Debug)]
 struct CreatedResponse {
     id: String,
 }
+
+#[derive(Deserialize, Debug)]
+struct SourceResponse {
+    source: String,
+}
@@ -0,0 +1,79 @@
+use clap::ValueEnum;
+use flate2::Compression;
+use flate2::read::{GzDecoder, GzEncoder};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{self, BufReader, Read};
+
+/// Encoding of blob content compressed by the CLI before upload.
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentEncoding {
+    Gzip,
+    Zstd,
+}
+
+impl fmt::Display for ContentEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentEncoding::Gzip => write!(f, "gzip"),
+            ContentEncoding::Zstd => write!(f, "zstd"),
+        }
+    }
+}
+
+impl ContentEncoding {
+    /// Wraps the reader so that reading from it yields compressed content.
+    pub fn encoder(
+        self,
+        reader: Box<dyn Read + Send + 'static>,
+    ) -> io::Result<Box<dyn Read + Send + 'static>> {
+        Ok(match self {
+            ContentEncoding::Gzip => Box::new(GzEncoder::new(reader, Compression::default())),
+            ContentEncoding::Zstd => Box::new(zstd::stream::read::Encoder::new(reader, 0)?),
+        })
+    }
+
+    /// Wraps the reader so that reading from it yields decompressed content.
+    pub fn decoder(
+        self,
+        reader: Box<dyn Read + Send + Sync + 'static>,
+    ) -> io::Result<Box<dyn Read + Send + Sync + 'static>> {
+        Ok(match self {
+            ContentEncoding::Gzip => Box::new(GzDecoder::new(reader)),
+            ContentEncoding::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(
+                BufReader::new(reader),
+            )?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let content = "subdomain.example.com\n".repeat(1000);
+
+        for encoding in [ContentEncoding::Gzip, ContentEncoding::Zstd] {
+            let mut compressed = Vec::new();
+            encoding
+                .encoder(Box::new(io::Cursor::new(content.clone().into_bytes())))
+                .and_then(|mut r| r.read_to_end(&mut compressed))
+                .unwrap_or_else(|err| panic!("{encoding}: failed to compress: {err:?}"));
+            assert!(
+                compressed.len() < content.len() / 10,
+                "{encoding}: expected content to shrink, got {} bytes",
+                compressed.len()
+            );
+
+            let mut decompressed = String::new();
+            encoding
+                .decoder(Box::new(io::Cursor::new(compressed)))
+                .and_then(|mut r| r.read_to_string(&mut decompressed))
+                .unwrap_or_else(|err| panic!("{encoding}: failed to decompress: {err:?}"));
+            assert_eq!(decompressed, content, "{encoding}: content differs");
+        }
+    }
+}
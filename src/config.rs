@@ -0,0 +1,119 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::{env, fs, io};
+
+type Result<T> = std::result::Result<T, String>;
+
+/// Defaults for global options, read from `config.json` in the config directory.
+///
+/// Flags and environment variables take precedence over it.
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Config {
+    /// Default of `--limit-rate`, e.g. `"10M"`
+    pub limit_rate: Option<String>,
+
+    /// Maximum size of the download cache, e.g. `"2G"`
+    pub cache_max_size: Option<String>,
+
+    /// Keys this version does not know, e.g. typos or keys of newer versions
+    #[serde(flatten)]
+    unknown: BTreeMap<String, Value>,
+}
+
+impl Config {
+    /// Loads the config file, falling back to the defaults when there is none.
+    pub fn load() -> Result<Config> {
+        match config_dir() {
+            Some(dir) => Config::load_from(&dir.join("config.json")),
+            None => Ok(Config::default()),
+        }
+    }
+
+    /// Loads the config file, warning about the keys it ignores.
+    fn load_from(path: &Path) -> Result<Config> {
+        match fs::read(path) {
+            Ok(content) => {
+                let config: Config = serde_json::from_slice(&content)
+                    .map_err(|err| format!("Invalid config file '{}': {err}", path.display()))?;
+                for key in config.unknown.keys() {
+                    eprintln!(
+                        "Ignoring unknown key '{key}' in config file '{}'",
+                        path.display()
+                    );
+                }
+                Ok(config)
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(err) => Err(format!(
+                "Failed to read config file '{}': {err:?}",
+                path.display()
+            )),
+        }
+    }
+}
+
+/// Per-user config directory of the CLI, `$XDG_CONFIG_HOME/bh` or `~/.config/bh`.
+pub fn config_dir() -> Option<PathBuf> {
+    user_dir("XDG_CONFIG_HOME", ".config", "APPDATA")
+}
+
+/// Per-user cache directory of the CLI, `$XDG_CACHE_HOME/bh` or `~/.cache/bh`.
+pub fn cache_dir() -> Option<PathBuf> {
+    user_dir("XDG_CACHE_HOME", ".cache", "LOCALAPPDATA")
+}
+
+/// Creates the directory and its parents, the directory itself only
+/// accessible by the current user.
+pub fn create_private_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)
+}
+
+/// Follows the XDG base directory spec, with a Windows fallback when there is no home.
+fn user_dir(xdg: &str, home_relative: &str, windows: &str) -> Option<PathBuf> {
+    let non_empty = |key| env::var_os(key).filter(|value| !value.is_empty());
+    non_empty(xdg)
+        .map(PathBuf::from)
+        .or_else(|| non_empty("HOME").map(|home| Path::new(&home).join(home_relative)))
+        .or_else(|| non_empty(windows).map(PathBuf::from))
+        .map(|dir| dir.join("bh"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_load_from() {
+        let path = env::temp_dir().join(Uuid::now_v7().to_string());
+
+        let missing = Config::load_from(&path);
+        fs::write(&path, r#"{"limitRate": "1M"}"#).expect("failed to write config");
+        let loaded = Config::load_from(&path);
+        fs::write(&path, r#"{"limit_rate": "1M"}"#).expect("failed to write config");
+        let unknown = Config::load_from(&path);
+        fs::remove_file(&path).ok();
+
+        assert_eq!(missing, Ok(Config::default()));
+        assert_eq!(
+            loaded,
+            Ok(Config {
+                limit_rate: Some("1M".to_string()),
+                ..Config::default()
+            })
+        );
+        let unknown = unknown.expect("unknown keys to be ignored");
+        assert_eq!(unknown.limit_rate, None);
+        assert_eq!(
+            unknown.unknown.keys().collect::<Vec<_>>(),
+            vec!["limit_rate"]
+        );
+    }
+}
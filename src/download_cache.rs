@@ -0,0 +1,287 @@
+use crate::config;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+
+type Result<T> = std::result::Result<T, String>;
+
+/// Default of the `cacheMaxSize` config key.
+pub const DEFAULT_MAX_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Distinguishes the temporary files of downloads running at the same time.
+static DOWNLOADS: AtomicU64 = AtomicU64::new(0);
+
+/// Local copies of downloaded files, revalidated with their ETag.
+///
+/// Files are stored as they were downloaded, so encrypted blobs stay
+/// encrypted on disk. Once the cached files add up to more than the maximum
+/// size, the least recently used ones are removed.
+#[derive(Debug, Clone)]
+pub struct DownloadCache {
+    dir: PathBuf,
+    max_size: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Entry {
+    key: String,
+    etag: String,
+    size: u64,
+}
+
+impl DownloadCache {
+    pub fn new(dir: PathBuf, max_size: u64) -> DownloadCache {
+        DownloadCache { dir, max_size }
+    }
+
+    /// ETag of the cached copy of `key`, if there is one.
+    pub fn etag(&self, key: &str) -> Option<String> {
+        let (content, meta) = self.paths(key);
+        let entry: Entry = serde_json::from_slice(&fs::read(meta).ok()?).ok()?;
+        let size = fs::metadata(content).ok()?.len();
+        (entry.key == key && entry.size == size).then_some(entry.etag)
+    }
+
+    /// Opens the cached copy of `key`, marking it as recently used.
+    pub fn open(&self, key: &str) -> io::Result<fs::File> {
+        let (content, meta) = self.paths(key);
+        let file = fs::File::open(content)?;
+        fs::File::options()
+            .write(true)
+            .open(meta)?
+            .set_modified(SystemTime::now())?;
+        Ok(file)
+    }
+
+    /// Wraps the downloaded content so that reading all of it stores it
+    /// under `key`.
+    ///
+    /// Content that is only read partly, fails to be written or is larger
+    /// than the cache is not stored.
+    pub fn fill<R: Read>(&self, key: &str, etag: &str, reader: R) -> Filling<R> {
+        let (content, meta) = self.paths(key);
+        let temp = content.with_extension(format!(
+            "{}-{}.part",
+            process::id(),
+            DOWNLOADS.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = config::create_private_dir(&self.dir)
+            .and_then(|()| fs::File::create(&temp))
+            .ok();
+
+        Filling {
+            inner: reader,
+            file,
+            temp,
+            content,
+            meta,
+            entry: Entry {
+                key: key.to_string(),
+                etag: etag.to_string(),
+                size: 0,
+            },
+            cache: self.clone(),
+        }
+    }
+
+    /// Removes every cached file, returning the number of bytes freed.
+    pub fn clean(&self) -> Result<u64> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => {
+                return Err(format!(
+                    "Failed to read cache directory '{}': {err:?}",
+                    self.dir.display()
+                ));
+            }
+        };
+
+        let mut freed = 0;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let size = entry.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+            fs::remove_file(&path)
+                .map_err(|err| format!("Failed to remove '{}': {err:?}", path.display()))?;
+            freed += size;
+        }
+        Ok(freed)
+    }
+
+    /// Content and metadata paths of the key, named by its hash.
+    fn paths(&self, key: &str) -> (PathBuf, PathBuf) {
+        let name = format!("{:x}", Sha256::digest(key.as_bytes()));
+        (self.dir.join(&name), self.dir.join(format!("{name}.json")))
+    }
+
+    /// Removes the least recently used files until the cache fits its maximum size.
+    fn evict(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+        let mut cached = entries
+            .flatten()
+            .filter_map(|entry| {
+                let meta = entry.path();
+                if meta.extension()? != "json" {
+                    return None;
+                }
+                let used = entry.metadata().ok()?.modified().ok()?;
+                let entry: Entry = serde_json::from_slice(&fs::read(&meta).ok()?).ok()?;
+                Some((used, entry.size, meta))
+            })
+            .collect::<Vec<_>>();
+        cached.sort();
+
+        let mut total = cached.iter().map(|(_, size, _)| size).sum::<u64>();
+        for (_, size, meta) in cached {
+            if total <= self.max_size {
+                break;
+            }
+            fs::remove_file(meta.with_extension("")).ok();
+            fs::remove_file(&meta).ok();
+            total -= size;
+        }
+    }
+}
+
+/// Reader storing what it reads in the cache once it reaches the end.
+pub struct Filling<R> {
+    inner: R,
+    file: Option<fs::File>,
+    temp: PathBuf,
+    content: PathBuf,
+    meta: PathBuf,
+    entry: Entry,
+    cache: DownloadCache,
+}
+
+impl<R> Filling<R> {
+    fn abandon(&mut self) {
+        if self.file.take().is_some() {
+            fs::remove_file(&self.temp).ok();
+        }
+    }
+
+    fn commit(&mut self) {
+        let Some(file) = self.file.take() else {
+            return;
+        };
+        drop(file);
+        let stored = fs::rename(&self.temp, &self.content).is_ok()
+            && serde_json::to_vec(&self.entry)
+                .ok()
+                .is_some_and(|meta| fs::write(&self.meta, meta).is_ok());
+        if stored {
+            self.cache.evict();
+        } else {
+            fs::remove_file(&self.temp).ok();
+            fs::remove_file(&self.content).ok();
+        }
+    }
+}
+
+impl<R: Read> Read for Filling<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if n == 0 && !buf.is_empty() {
+            self.commit();
+            return Ok(0);
+        }
+
+        if let Some(file) = &mut self.file {
+            self.entry.size += n as u64;
+            if self.entry.size > self.cache.max_size || file.write_all(&buf[..n]).is_err() {
+                self.abandon();
+            }
+        }
+        Ok(n)
+    }
+}
+
+impl<R> Drop for Filling<R> {
+    fn drop(&mut self) {
+        self.abandon();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use uuid::Uuid;
+
+    fn cache(max_size: u64) -> DownloadCache {
+        DownloadCache::new(env::temp_dir().join(Uuid::now_v7().to_string()), max_size)
+    }
+
+    fn fill(cache: &DownloadCache, key: &str, etag: &str, content: &[u8]) {
+        let mut reader = cache.fill(key, etag, content);
+        io::copy(&mut reader, &mut io::sink()).expect("copy to succeed");
+    }
+
+    #[test]
+    fn test_fill_and_open() {
+        let cache = cache(1024);
+
+        fill(&cache, "blob:a.txt", "\"abc\"", b"a.example.com\n");
+        let mut content = String::new();
+        cache
+            .open("blob:a.txt")
+            .and_then(|mut file| file.read_to_string(&mut content))
+            .expect("cached file to open");
+        let etag = cache.etag("blob:a.txt");
+        let other = cache.etag("blob:b.txt");
+        fs::remove_dir_all(&cache.dir).ok();
+
+        assert_eq!(etag.as_deref(), Some("\"abc\""));
+        assert_eq!(content, "a.example.com\n");
+        assert_eq!(other, None);
+    }
+
+    #[test]
+    fn test_fill_partial_read() {
+        let cache = cache(1024);
+
+        let mut reader = cache.fill("blob:a.txt", "\"abc\"", &b"a.example.com\n"[..]);
+        reader.read_exact(&mut [0; 4]).expect("read to succeed");
+        drop(reader);
+        let etag = cache.etag("blob:a.txt");
+        let leftovers = fs::read_dir(&cache.dir).map(|dir| dir.count()).unwrap_or(0);
+        fs::remove_dir_all(&cache.dir).ok();
+
+        assert_eq!(etag, None);
+        assert_eq!(leftovers, 0, "expected the partial file to be removed");
+    }
+
+    #[test]
+    fn test_evict_and_clean() {
+        let cache = cache(10);
+
+        fill(&cache, "blob:large.txt", "\"1\"", &[0; 11]);
+        fill(&cache, "blob:old.txt", "\"2\"", &[0; 6]);
+        fs::File::options()
+            .write(true)
+            .open(cache.paths("blob:old.txt").1)
+            .and_then(|meta| meta.set_modified(SystemTime::UNIX_EPOCH))
+            .expect("metadata to be updated");
+        fill(&cache, "blob:new.txt", "\"3\"", &[0; 6]);
+        let large = cache.etag("blob:large.txt");
+        let old = cache.etag("blob:old.txt");
+        let new = cache.etag("blob:new.txt");
+        let freed = cache.clean();
+        let after_clean = cache.etag("blob:new.txt");
+        fs::remove_dir_all(&cache.dir).ok();
+
+        assert_eq!(large, None, "larger than the cache");
+        assert_eq!(old, None, "least recently used");
+        assert_eq!(new.as_deref(), Some("\"3\""));
+        assert_eq!(freed.ok().map(|freed| freed >= 6), Some(true));
+        assert_eq!(after_clean, None);
+    }
+}
@@ -0,0 +1,145 @@
+use age::x25519;
+use std::io::{self, Read};
+use std::path::Path;
+use std::thread::{self, JoinHandle};
+use std::{fs, iter};
+
+/// Locally held age X25519 identity used to encrypt blobs before upload.
+///
+/// Identity files are the ones generated by `age-keygen`.
+pub struct Identity(x25519::Identity);
+
+impl Identity {
+    pub fn load(path: &Path) -> Result<Identity, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read identity file '{}': {err:?}", path.display()))?;
+
+        content
+            .lines()
+            .map(str::trim)
+            .find(|line| line.starts_with("AGE-SECRET-KEY-"))
+            .ok_or_else(|| format!("Identity file '{}' has no age secret key", path.display()))?
+            .parse()
+            .map(Identity)
+            .map_err(|err| format!("Invalid identity in '{}': {err}", path.display()))
+    }
+
+    /// Wraps the reader so that reading from it yields the content encrypted to this identity.
+    ///
+    /// Encryption runs on a separate thread writing into a pipe, and any error it hits is
+    /// returned once the pipe is drained.
+    pub fn encryptor(
+        &self,
+        mut reader: Box<dyn Read + Send + 'static>,
+    ) -> io::Result<Box<dyn Read + Send + 'static>> {
+        let recipient = self.0.to_public();
+        let encryptor =
+            age::Encryptor::with_recipients(iter::once(&recipient as &dyn age::Recipient))
+                .map_err(io::Error::other)?;
+
+        let (pipe, pipe_writer) = io::pipe()?;
+        let handle = thread::spawn(move || {
+            let mut writer = encryptor.wrap_output(pipe_writer)?;
+            io::copy(&mut reader, &mut writer)?;
+            writer.finish()?;
+            Ok(())
+        });
+
+        Ok(Box::new(ThreadReader {
+            pipe,
+            handle: Some(handle),
+        }))
+    }
+
+    /// Wraps the reader so that reading from it yields the decrypted content.
+    pub fn decryptor(
+        &self,
+        reader: Box<dyn Read + Send + Sync + 'static>,
+    ) -> io::Result<Box<dyn Read + Send + Sync + 'static>> {
+        let decryptor = age::Decryptor::new(reader).map_err(io::Error::other)?;
+        let reader = decryptor
+            .decrypt(iter::once(&self.0 as &dyn age::Identity))
+            .map_err(io::Error::other)?;
+        Ok(Box::new(reader))
+    }
+}
+
+struct ThreadReader {
+    pipe: io::PipeReader,
+    handle: Option<JoinHandle<io::Result<()>>>,
+}
+
+impl Read for ThreadReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.pipe.read(buf)?;
+        if n == 0
+            && let Some(handle) = self.handle.take()
+        {
+            handle
+                .join()
+                .map_err(|_| io::Error::other("encryption thread panicked"))??;
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_round_trip() {
+        let identity = Identity(x25519::Identity::generate());
+        let content = b"admin:hunter2\n".repeat(100_000);
+
+        let mut ciphertext = Vec::new();
+        identity
+            .encryptor(Box::new(io::Cursor::new(content.clone())))
+            .and_then(|mut r| r.read_to_end(&mut ciphertext))
+            .unwrap_or_else(|err| panic!("failed to encrypt: {err:?}"));
+        assert!(
+            !ciphertext.windows(13).any(|w| w == b"admin:hunter2"),
+            "ciphertext contains plaintext"
+        );
+
+        let mut plaintext = Vec::new();
+        identity
+            .decryptor(Box::new(io::Cursor::new(ciphertext.clone())))
+            .and_then(|mut r| r.read_to_end(&mut plaintext))
+            .unwrap_or_else(|err| panic!("failed to decrypt: {err:?}"));
+        assert!(plaintext == content, "plaintext differs");
+
+        let other = Identity(x25519::Identity::generate());
+        let result = other.decryptor(Box::new(io::Cursor::new(ciphertext)));
+        assert!(result.is_err(), "expected other identity to fail");
+    }
+
+    #[test]
+    fn test_load() {
+        let path = env::temp_dir().join(Uuid::now_v7().to_string());
+        let identity = x25519::Identity::generate();
+        fs::write(
+            &path,
+            format!(
+                "# created: 2025-01-01T00:00:00Z\n# public key: {}\n{}\n",
+                identity.to_public(),
+                age::secrecy::ExposeSecret::expose_secret(&identity.to_string())
+            ),
+        )
+        .expect("failed to write identity");
+
+        let loaded = Identity::load(&path);
+        fs::write(&path, "not a key\n").expect("failed to write identity");
+        let invalid = Identity::load(&path);
+        fs::remove_file(&path).ok();
+
+        let loaded = loaded.unwrap_or_else(|err| panic!("expected ok, got {err}"));
+        assert_eq!(
+            loaded.0.to_public().to_string(),
+            identity.to_public().to_string()
+        );
+        assert!(invalid.is_err(), "expected error, got ok");
+    }
+}
@@ -0,0 +1,492 @@
+use serde_json::Value;
+
+/// Part of a string that may contain `${{ ... }}` expressions.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Part {
+    Text(String),
+    Expr(Expr),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expr {
+    Literal(Value),
+    Ident(String),
+    Property(Box<Expr>, String),
+    Index(Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+    Not(Box<Expr>),
+    Binary(Box<Expr>, BinOp, Box<Expr>),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+/// Splits a string into text and the expressions embedded in `${{ ... }}`.
+pub fn parse_template(s: &str) -> Result<Vec<Part>, String> {
+    let mut parts = Vec::new();
+    let mut rest = s;
+
+    while let Some(start) = rest.find("${{") {
+        if start > 0 {
+            parts.push(Part::Text(rest[..start].to_string()));
+        }
+
+        let body = &rest[start + 3..];
+        let end = body
+            .find("}}")
+            .ok_or_else(|| format!("Unterminated expression '{}'", &rest[start..]))?;
+
+        parts.push(Part::Expr(parse(&body[..end])?));
+        rest = &body[end + 2..];
+    }
+
+    if !rest.is_empty() {
+        parts.push(Part::Text(rest.to_string()));
+    }
+
+    Ok(parts)
+}
+
+/// Parses a single expression, without the surrounding `${{ }}`.
+pub fn parse(input: &str) -> Result<Expr, String> {
+    let tokens = tokenize(input)?;
+    if tokens.is_empty() {
+        return Err("Empty expression".to_string());
+    }
+
+    let mut parser = ExprParser { tokens, pos: 0 };
+    let expr = parser.or()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(format!(
+            "Unexpected {token} in expression '{}'",
+            input.trim()
+        )),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Ident(String),
+    Literal(Value),
+    Op(&'static str),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Ident(ident) => write!(f, "'{ident}'"),
+            Token::Literal(value) => write!(f, "'{value}'"),
+            Token::Op(op) => write!(f, "'{op}'"),
+        }
+    }
+}
+
+const OPS: [&str; 15] = [
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "!", "(", ")", "[", "]", ".", ",",
+];
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(i, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '\'' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, '\'')) => {
+                        // '' is an escaped quote
+                        if let Some((_, '\'')) = chars.peek() {
+                            chars.next();
+                            s.push('\'');
+                        } else {
+                            break;
+                        }
+                    }
+                    Some((_, c)) => s.push(c),
+                    None => return Err(format!("Unterminated string in expression '{input}'")),
+                }
+            }
+            tokens.push(Token::Literal(Value::String(s)));
+        } else if c.is_ascii_digit() {
+            let mut end = i;
+            while let Some(&(j, c)) = chars.peek() {
+                if !(c.is_ascii_digit() || c == '.') {
+                    break;
+                }
+                end = j + c.len_utf8();
+                chars.next();
+            }
+            let literal = &input[i..end];
+            let number = match literal.parse::<u64>() {
+                Ok(n) => serde_json::Number::from(n),
+                Err(_) => literal
+                    .parse::<f64>()
+                    .ok()
+                    .and_then(serde_json::Number::from_f64)
+                    .ok_or_else(|| format!("Invalid number '{literal}'"))?,
+            };
+            tokens.push(Token::Literal(Value::Number(number)));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut end = i;
+            while let Some(&(j, c)) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+                    break;
+                }
+                end = j + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(match &input[i..end] {
+                "true" => Token::Literal(Value::Bool(true)),
+                "false" => Token::Literal(Value::Bool(false)),
+                "null" => Token::Literal(Value::Null),
+                ident => Token::Ident(ident.to_string()),
+            });
+        } else {
+            let op = OPS
+                .iter()
+                .find(|op| input[i..].starts_with(**op))
+                .ok_or_else(|| format!("Unexpected character '{c}' in expression '{input}'"))?;
+            for _ in 0..op.len() {
+                chars.next();
+            }
+            tokens.push(Token::Op(op));
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct ExprParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl ExprParser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Op(o)) if *o == op) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), String> {
+        if self.eat(op) {
+            return Ok(());
+        }
+        match self.peek() {
+            Some(token) => Err(format!("Expected '{op}', found {token}")),
+            None => Err(format!("Expected '{op}', found end of expression")),
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.and()?;
+        while self.eat("||") {
+            lhs = Expr::Binary(Box::new(lhs), BinOp::Or, Box::new(self.and()?));
+        }
+        Ok(lhs)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.comparison()?;
+        while self.eat("&&") {
+            lhs = Expr::Binary(Box::new(lhs), BinOp::And, Box::new(self.comparison()?));
+        }
+        Ok(lhs)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let lhs = self.unary()?;
+        let op = match self.peek() {
+            Some(Token::Op("==")) => BinOp::Eq,
+            Some(Token::Op("!=")) => BinOp::Ne,
+            Some(Token::Op("<")) => BinOp::Lt,
+            Some(Token::Op("<=")) => BinOp::Le,
+            Some(Token::Op(">")) => BinOp::Gt,
+            Some(Token::Op(">=")) => BinOp::Ge,
+            _ => return Ok(lhs),
+        };
+        self.pos += 1;
+        Ok(Expr::Binary(Box::new(lhs), op, Box::new(self.unary()?)))
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.unary()?)));
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Expr, String> {
+        let mut expr = self.primary()?;
+        loop {
+            if self.eat(".") {
+                match self.next() {
+                    Some(Token::Ident(name)) => expr = Expr::Property(Box::new(expr), name),
+                    Some(token) => return Err(format!("Expected property name, found {token}")),
+                    None => return Err("Expected property name, found end of expression".into()),
+                }
+            } else if self.eat("[") {
+                let index = self.or()?;
+                self.expect("]")?;
+                expr = Expr::Index(Box::new(expr), Box::new(index));
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Literal(value)) => Ok(Expr::Literal(value)),
+            Some(Token::Ident(name)) => {
+                if !self.eat("(") {
+                    return Ok(Expr::Ident(name));
+                }
+                let mut args = Vec::new();
+                if !self.eat(")") {
+                    loop {
+                        args.push(self.or()?);
+                        if self.eat(")") {
+                            break;
+                        }
+                        self.expect(",")?;
+                    }
+                }
+                Ok(Expr::Call(name, args))
+            }
+            Some(Token::Op("(")) => {
+                let expr = self.or()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Some(token) => Err(format!("Unexpected {token}")),
+            None => Err("Unexpected end of expression".to_string()),
+        }
+    }
+}
+
+/// Renders a string with embedded expressions against the given context.
+pub fn render(s: &str, context: &Value) -> Result<String, String> {
+    let mut out = String::new();
+    for part in parse_template(s)? {
+        match part {
+            Part::Text(text) => out.push_str(&text),
+            Part::Expr(expr) => match evaluate(&expr, context)? {
+                Value::Null => {}
+                Value::String(s) => out.push_str(&s),
+                value => out.push_str(&value.to_string()),
+            },
+        }
+    }
+    Ok(out)
+}
+
+/// Evaluates an expression, looking identifiers up in the context object.
+///
+/// Missing properties evaluate to null.
+pub fn evaluate(expr: &Expr, context: &Value) -> Result<Value, String> {
+    match expr {
+        Expr::Literal(value) => Ok(value.clone()),
+        Expr::Ident(name) => Ok(context.get(name).cloned().unwrap_or(Value::Null)),
+        Expr::Property(expr, name) => Ok(evaluate(expr, context)?
+            .get(name)
+            .cloned()
+            .unwrap_or(Value::Null)),
+        Expr::Index(expr, index) => {
+            let value = evaluate(expr, context)?;
+            let found = match evaluate(index, context)? {
+                Value::String(key) => value.get(key),
+                Value::Number(n) => n.as_u64().and_then(|i| value.get(i as usize)),
+                _ => None,
+            };
+            Ok(found.cloned().unwrap_or(Value::Null))
+        }
+        Expr::Not(expr) => Ok(Value::Bool(!truthy(&evaluate(expr, context)?))),
+        Expr::Binary(lhs, BinOp::And, rhs) => {
+            let lhs = evaluate(lhs, context)?;
+            if truthy(&lhs) {
+                evaluate(rhs, context)
+            } else {
+                Ok(lhs)
+            }
+        }
+        Expr::Binary(lhs, BinOp::Or, rhs) => {
+            let lhs = evaluate(lhs, context)?;
+            if truthy(&lhs) {
+                Ok(lhs)
+            } else {
+                evaluate(rhs, context)
+            }
+        }
+        Expr::Binary(lhs, op, rhs) => {
+            let lhs = evaluate(lhs, context)?;
+            let rhs = evaluate(rhs, context)?;
+            let ordering = match (&lhs, &rhs) {
+                (Value::Number(a), Value::Number(b)) => a.as_f64().partial_cmp(&b.as_f64()),
+                (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+                _ => None,
+            };
+            let result = match op {
+                BinOp::Eq => lhs == rhs,
+                BinOp::Ne => lhs != rhs,
+                BinOp::Lt => ordering.is_some_and(|o| o.is_lt()),
+                BinOp::Le => ordering.is_some_and(|o| o.is_le()),
+                BinOp::Gt => ordering.is_some_and(|o| o.is_gt()),
+                BinOp::Ge => ordering.is_some_and(|o| o.is_ge()),
+                BinOp::And | BinOp::Or => unreachable!("handled above"),
+            };
+            Ok(Value::Bool(result))
+        }
+        Expr::Call(name, args) => {
+            let args = args
+                .iter()
+                .map(|arg| evaluate(arg, context))
+                .collect::<Result<Vec<_>, _>>()?;
+            call(name, &args)
+        }
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(_) | Value::Object(_) => true,
+    }
+}
+
+fn call(name: &str, args: &[Value]) -> Result<Value, String> {
+    let strings = || match args {
+        [Value::String(a), Value::String(b)] => Ok((a.as_str(), b.as_str())),
+        _ => Err(format!("Function '{name}' expects two strings")),
+    };
+
+    match name {
+        "contains" => match args {
+            [Value::Array(items), item] => Ok(Value::Bool(items.contains(item))),
+            _ => strings().map(|(a, b)| Value::Bool(a.contains(b))),
+        },
+        "startsWith" => strings().map(|(a, b)| Value::Bool(a.starts_with(b))),
+        "endsWith" => strings().map(|(a, b)| Value::Bool(a.ends_with(b))),
+        _ => Err(format!("Unknown function '{name}'")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_template() {
+        let parts = parse_template("echo ${{ inputs.domain }} > out.txt")
+            .unwrap_or_else(|err| panic!("expected ok, got {err}"));
+        assert_eq!(
+            parts,
+            vec![
+                Part::Text("echo ".to_string()),
+                Part::Expr(Expr::Property(
+                    Box::new(Expr::Ident("inputs".to_string())),
+                    "domain".to_string()
+                )),
+                Part::Text(" > out.txt".to_string()),
+            ]
+        );
+
+        let parts = parse_template("no expressions").expect("expected ok");
+        assert_eq!(parts, vec![Part::Text("no expressions".to_string())]);
+    }
+
+    #[test]
+    fn test_render() {
+        let context = serde_json::json!({
+            "inputs": { "domain": "example.com", "deep": false, "ports": ["80", "443"] },
+            "vars": { "api-key": "secret" },
+        });
+
+        for (input, want) in [
+            ("echo ${{ inputs.domain }}", "echo example.com"),
+            ("${{ vars['api-key'] }}", "secret"),
+            ("${{ inputs.missing }}", ""),
+            (
+                "${{ !inputs.deep && inputs.domain == 'example.com' }}",
+                "true",
+            ),
+            ("${{ inputs.deep || 'shallow' }}", "shallow"),
+            ("${{ contains(inputs.ports, '443') }}", "true"),
+            ("${{ endsWith(inputs.domain, '.com') }}", "true"),
+            ("${{ inputs.ports[1] }}", "443"),
+            ("${{ 2 > 10 }}", "false"),
+        ] {
+            let result = render(input, &context);
+            assert_eq!(result.as_deref(), Ok(want), "{input}");
+        }
+
+        let result = render("${{ unknown(1) }}", &context);
+        assert!(result.is_err(), "expected error, got {result:?}");
+    }
+
+    #[test]
+    fn test_parse_valid_expressions() {
+        for input in [
+            "inputs.domain",
+            "vars['api-key']",
+            "!inputs.dry_run && vars.MODE == 'full'",
+            "contains(inputs.targets, 'example.com') || (1 >= 0.5)",
+            "'it''s'",
+            "fn()",
+        ] {
+            let result = parse(input);
+            assert!(result.is_ok(), "{input}: expected ok, got {result:?}");
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid_expressions() {
+        for input in [
+            "",
+            "   ",
+            "inputs.",
+            "inputs.domain ==",
+            "vars['key'",
+            "'unterminated",
+            "a b",
+            "fn(a,",
+            "inputs.domain $",
+        ] {
+            let result = parse(input);
+            assert!(result.is_err(), "{input}: expected error, got {result:?}");
+        }
+
+        let result = parse_template("echo ${{ inputs.domain");
+        assert!(result.is_err(), "expected error, got {result:?}");
+    }
+}
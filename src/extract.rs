@@ -0,0 +1,613 @@
+use crate::transfer;
+use flate2::CrcReader;
+use flate2::bufread::DeflateDecoder;
+use flate2::read::MultiGzDecoder;
+use indicatif::HumanBytes;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+
+type Result<T> = std::result::Result<T, String>;
+
+/// Extraction stops once an archive has more entries than this.
+const MAX_ENTRIES: usize = 100_000;
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x0807_4b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+const ZSTD: u16 = 93;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Zip,
+    Tar,
+    TarGz,
+    TarZst,
+}
+
+impl Format {
+    /// Picks the archive format from the file name extension.
+    pub fn from_name(name: &str) -> Result<Format> {
+        let lower = name.to_ascii_lowercase();
+        let name_ends_with = |ext: &str| lower.ends_with(ext);
+        if name_ends_with(".zip") {
+            Ok(Format::Zip)
+        } else if name_ends_with(".tar") {
+            Ok(Format::Tar)
+        } else if name_ends_with(".tar.gz") || name_ends_with(".tgz") {
+            Ok(Format::TarGz)
+        } else if name_ends_with(".tar.zst") || name_ends_with(".tzst") {
+            Ok(Format::TarZst)
+        } else {
+            Err(format!(
+                "Cannot extract '{name}', expected a .zip, .tar, .tar.gz, .tgz or .tar.zst file"
+            ))
+        }
+    }
+}
+
+/// Unpacks the archive into `root` while it is read.
+///
+/// Entries are written through [`transfer::confined_output`], so absolute
+/// paths and paths escaping `root` are rejected. Links and special files are
+/// skipped. Extraction fails once the files written add up to more than
+/// `max_size` bytes, so that a small archive cannot fill the disk, or once it
+/// has more than [`MAX_ENTRIES`] entries. On failure, the files and
+/// directories created so far are removed again.
+pub fn extract<R>(reader: R, format: Format, root: &Path, max_size: u64) -> Result<()>
+where
+    R: Read,
+{
+    let mut budget = Budget::new(max_size);
+    if fs::symlink_metadata(root).is_err() {
+        budget.created.push(root.to_path_buf());
+    }
+    let result = fs::create_dir_all(root)
+        .map_err(|err| format!("Failed to create directory '{}': {err:?}", root.display()))
+        .and_then(|()| extract_format(reader, format, root, &mut budget));
+    if result.is_err() {
+        budget.remove_created();
+    }
+    result
+}
+
+fn extract_format<R>(reader: R, format: Format, root: &Path, budget: &mut Budget) -> Result<()>
+where
+    R: Read,
+{
+    match format {
+        Format::Zip => extract_zip(BufReader::new(reader), root, budget),
+        Format::Tar => extract_tar(reader, root, budget),
+        Format::TarGz => extract_tar(MultiGzDecoder::new(reader), root, budget),
+        Format::TarZst => extract_tar(
+            zstd::Decoder::new(reader).map_err(|err| format!("Failed to decode zstd: {err:?}"))?,
+            root,
+            budget,
+        ),
+    }
+}
+
+struct Budget {
+    max_size: u64,
+    written: u64,
+    entries: usize,
+    /// Topmost path each entry created, in creation order
+    created: Vec<PathBuf>,
+}
+
+impl Budget {
+    fn new(max_size: u64) -> Budget {
+        Budget {
+            max_size,
+            written: 0,
+            entries: 0,
+            created: Vec::new(),
+        }
+    }
+
+    fn add_entry(&mut self) -> Result<()> {
+        self.entries += 1;
+        if self.entries > MAX_ENTRIES {
+            return Err(format!(
+                "Archive has more than {MAX_ENTRIES} entries, refusing to extract"
+            ));
+        }
+        Ok(())
+    }
+
+    /// Writes the entry content to the file, returning the number of bytes written.
+    fn write_file<R>(&mut self, reader: R, output: &Path) -> Result<u64>
+    where
+        R: Read,
+    {
+        let remaining = self.max_size.saturating_sub(self.written);
+        let mut fwriter = fs::File::create(output)
+            .map_err(|err| format!("Failed to create file '{}': {err:?}", output.display()))?;
+        let written = io::copy(&mut reader.take(remaining + 1), &mut fwriter)
+            .map_err(|err| format!("Failed to write file '{}': {err:?}", output.display()))?;
+        if written > remaining {
+            return Err(format!(
+                "Archive expands to more than {}, refusing to extract",
+                HumanBytes(self.max_size)
+            ));
+        }
+
+        self.written += written;
+        Ok(written)
+    }
+
+    /// Resolves the entry output with [`transfer::confined_output`],
+    /// remembering the first missing path along the way as created.
+    fn output(&mut self, root: &Path, path: &str) -> Result<PathBuf> {
+        let created = Path::new(path)
+            .components()
+            .scan(root.to_path_buf(), |output, component| {
+                output.push(component);
+                Some(output.clone())
+            })
+            .find(|output| fs::symlink_metadata(output).is_err());
+        let output = transfer::confined_output(root, path)?;
+        self.created.extend(created);
+        Ok(output)
+    }
+
+    fn create_dir(&mut self, root: &Path, name: &str) -> Result<()> {
+        let Some(path) = entry_path(name) else {
+            return Ok(());
+        };
+        let dir = self.output(root, path)?;
+        fs::create_dir_all(&dir)
+            .map_err(|err| format!("Failed to create directory '{}': {err:?}", dir.display()))
+    }
+
+    /// Removes what the extraction created, newest first.
+    fn remove_created(&self) {
+        for path in self.created.iter().rev() {
+            match fs::symlink_metadata(path) {
+                Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path).ok(),
+                Ok(_) => fs::remove_file(path).ok(),
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+/// Strips the leading `./` archivers commonly add, returning `None` for the
+/// entry standing for the archive root.
+fn entry_path(name: &str) -> Option<&str> {
+    let mut name = name.trim_end_matches('/');
+    while let Some(rest) = name.strip_prefix("./") {
+        name = rest;
+    }
+    (!name.is_empty() && name != ".").then_some(name)
+}
+
+fn extract_tar<R>(reader: R, root: &Path, budget: &mut Budget) -> Result<()>
+where
+    R: Read,
+{
+    let mut archive = tar::Archive::new(reader);
+    let entries = archive
+        .entries()
+        .map_err(|err| format!("Failed to read tar archive: {err:?}"))?;
+
+    for entry in entries {
+        let entry = entry.map_err(|err| format!("Failed to read tar entry: {err:?}"))?;
+        budget.add_entry()?;
+
+        let name = entry
+            .path()
+            .map_err(|err| format!("Invalid tar entry path: {err:?}"))?
+            .to_str()
+            .ok_or_else(|| "Tar entry path is not valid UTF-8".to_string())?
+            .to_string();
+
+        match entry.header().entry_type() {
+            tar::EntryType::Directory => budget.create_dir(root, &name)?,
+            tar::EntryType::Regular | tar::EntryType::Continuous => {
+                let Some(path) = entry_path(&name) else {
+                    continue;
+                };
+                let output = budget.output(root, path)?;
+                budget.write_file(entry, &output)?;
+            }
+            entry_type => eprintln!("Skipping {entry_type:?} entry '{name}'"),
+        }
+    }
+
+    Ok(())
+}
+
+fn extract_zip<R>(mut reader: R, root: &Path, budget: &mut Budget) -> Result<()>
+where
+    R: BufRead,
+{
+    loop {
+        match read_u32(&mut reader)? {
+            LOCAL_HEADER_SIGNATURE => budget.add_entry()?,
+            CENTRAL_HEADER_SIGNATURE | EOCD_SIGNATURE => break,
+            _ => return Err("Not a zip archive".to_string()),
+        }
+
+        let mut header = [0u8; 26];
+        read_exact(&mut reader, &mut header)?;
+        let flags = u16_at(&header, 2);
+        let method = u16_at(&header, 4);
+        let crc32 = u32_at(&header, 10);
+        let mut compressed_size = u64::from(u32_at(&header, 14));
+        let mut size = u64::from(u32_at(&header, 18));
+
+        let mut name = vec![0u8; usize::from(u16_at(&header, 22))];
+        read_exact(&mut reader, &mut name)?;
+        let name = String::from_utf8_lossy(&name).into_owned();
+        let mut extra = vec![0u8; usize::from(u16_at(&header, 24))];
+        read_exact(&mut reader, &mut extra)?;
+
+        if compressed_size == u64::from(u32::MAX) || size == u64::from(u32::MAX) {
+            (size, compressed_size) = zip64_sizes(&extra)
+                .ok_or_else(|| format!("Invalid zip64 extra field for '{name}'"))?;
+        }
+        if flags & 1 != 0 {
+            return Err(format!("'{name}' is encrypted"));
+        }
+
+        let has_descriptor = flags & 8 != 0;
+        if has_descriptor && method != DEFLATED {
+            return Err(format!(
+                "'{name}' cannot be extracted while streaming, its size is only known after its content"
+            ));
+        }
+
+        let output = match entry_path(&name) {
+            Some(_) if name.ends_with('/') => {
+                budget.create_dir(root, &name)?;
+                None
+            }
+            Some(path) => Some(budget.output(root, path)?),
+            None => None,
+        };
+
+        // Without a data descriptor the compressed size is known, otherwise
+        // the deflate stream ends on its own.
+        let limit = if has_descriptor {
+            u64::MAX
+        } else {
+            compressed_size
+        };
+        let mut data = Counted {
+            inner: (&mut reader).take(limit),
+            count: 0,
+        };
+        let content: Box<dyn Read + '_> = match method {
+            STORED => Box::new(&mut data),
+            DEFLATED => Box::new(DeflateDecoder::new(&mut data)),
+            ZSTD => Box::new(
+                zstd::Decoder::with_buffer(&mut data)
+                    .map_err(|err| format!("Failed to decode '{name}': {err:?}"))?,
+            ),
+            method => {
+                return Err(format!(
+                    "'{name}' uses unsupported compression method {method}"
+                ));
+            }
+        };
+
+        let mut content = CrcReader::new(content);
+        let written = match &output {
+            Some(output) => budget.write_file(&mut content, output)?,
+            None => io::copy(&mut content, &mut io::sink())
+                .map_err(|err| format!("Failed to read '{name}': {err:?}"))?,
+        };
+        let actual_crc32 = content.crc().sum();
+        drop(content);
+        // Skip what the decoder did not need, to stay aligned with the next header
+        if !has_descriptor {
+            io::copy(&mut data, &mut io::sink())
+                .map_err(|err| format!("Failed to read '{name}': {err:?}"))?;
+        }
+        let consumed = data.count;
+
+        let (crc32, size) = if has_descriptor {
+            read_data_descriptor(&mut reader, consumed, written)?
+        } else {
+            (crc32, size)
+        };
+        if written != size || actual_crc32 != crc32 {
+            return Err(format!("'{name}' does not match its size or checksum"));
+        }
+    }
+
+    // The central directory follows the last entry, read it so the whole
+    // download is accounted for.
+    io::copy(&mut reader, &mut io::sink())
+        .map_err(|err| format!("Failed to read zip archive: {err:?}"))?;
+    Ok(())
+}
+
+/// Reads the descriptor that follows the content of an entry, returning its
+/// CRC-32 and uncompressed size.
+///
+/// The sizes are 8 bytes for zip64 entries and 4 bytes otherwise, which the
+/// descriptor itself does not tell, so the 4 byte sizes are used when they
+/// match what was read.
+fn read_data_descriptor<R>(reader: &mut R, compressed_size: u64, size: u64) -> Result<(u32, u64)>
+where
+    R: Read,
+{
+    let mut crc32 = read_u32(reader)?;
+    if crc32 == DATA_DESCRIPTOR_SIGNATURE {
+        crc32 = read_u32(reader)?;
+    }
+
+    let mut sizes = [0u8; 8];
+    read_exact(reader, &mut sizes)?;
+    if u64::from(u32_at(&sizes, 0)) == compressed_size && u64::from(u32_at(&sizes, 4)) == size {
+        return Ok((crc32, size));
+    }
+
+    let mut size64 = [0u8; 8];
+    read_exact(reader, &mut size64)?;
+    Ok((crc32, u64::from_le_bytes(size64)))
+}
+
+/// Uncompressed and compressed sizes from the zip64 extra field of a local
+/// header, which holds both whenever it is present.
+fn zip64_sizes(mut extra: &[u8]) -> Option<(u64, u64)> {
+    while extra.len() >= 4 {
+        let id = u16_at(extra, 0);
+        let len = usize::from(u16_at(extra, 2));
+        let data = extra.get(4..4 + len)?;
+        if id == ZIP64_EXTRA_ID && len >= 16 {
+            let size = u64::from_le_bytes(data[0..8].try_into().ok()?);
+            let compressed_size = u64::from_le_bytes(data[8..16].try_into().ok()?);
+            return Some((size, compressed_size));
+        }
+        extra = &extra[4 + len..];
+    }
+    None
+}
+
+fn read_exact<R>(reader: &mut R, buf: &mut [u8]) -> Result<()>
+where
+    R: Read,
+{
+    reader
+        .read_exact(buf)
+        .map_err(|err| format!("Failed to read zip archive: {err:?}"))
+}
+
+fn read_u32<R>(reader: &mut R) -> Result<u32>
+where
+    R: Read,
+{
+    let mut buf = [0u8; 4];
+    read_exact(reader, &mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn u16_at(buf: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([buf[pos], buf[pos + 1]])
+}
+
+fn u32_at(buf: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(buf[pos..pos + 4].try_into().expect("4 bytes"))
+}
+
+/// Counts the bytes consumed from the inner reader.
+struct Counted<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R: BufRead> Read for Counted<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count += n as u64;
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for Counted<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        self.count += amount as u64;
+        self.inner.consume(amount);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::write::{DeflateEncoder, GzEncoder};
+    use flate2::{Compression, Crc};
+    use std::env;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use uuid::Uuid;
+
+    const MAX_SIZE: u64 = 1024 * 1024;
+
+    fn temp_dir() -> PathBuf {
+        env::temp_dir().join(Uuid::now_v7().to_string())
+    }
+
+    /// Builds a zip archive as streaming writers do, with the deflated
+    /// entries' sizes and CRC-32 in a data descriptor after their content.
+    fn build_zip(files: &[(&str, &[u8], u16)]) -> Vec<u8> {
+        let mut zip = Vec::new();
+        for (name, content, method) in files {
+            let mut crc = Crc::new();
+            crc.update(content);
+            let (flags, data) = match *method {
+                DEFLATED => {
+                    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                    encoder.write_all(content).unwrap();
+                    (8u16, encoder.finish().unwrap())
+                }
+                _ => (0u16, content.to_vec()),
+            };
+            let sizes = if flags & 8 != 0 {
+                [0u32, 0, 0]
+            } else {
+                [crc.sum(), data.len() as u32, content.len() as u32]
+            };
+
+            zip.extend(LOCAL_HEADER_SIGNATURE.to_le_bytes());
+            zip.extend([20, 0]);
+            zip.extend(flags.to_le_bytes());
+            zip.extend(method.to_le_bytes());
+            zip.extend([0; 4]);
+            for value in sizes {
+                zip.extend(value.to_le_bytes());
+            }
+            zip.extend((name.len() as u16).to_le_bytes());
+            zip.extend(0u16.to_le_bytes());
+            zip.extend(name.as_bytes());
+            zip.extend(&data);
+
+            if flags & 8 != 0 {
+                zip.extend(DATA_DESCRIPTOR_SIGNATURE.to_le_bytes());
+                zip.extend(crc.sum().to_le_bytes());
+                zip.extend((data.len() as u32).to_le_bytes());
+                zip.extend((content.len() as u32).to_le_bytes());
+            }
+        }
+        // Central directory contents are not read while streaming
+        zip.extend(CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+        zip.extend([0; 42]);
+        zip
+    }
+
+    #[test]
+    fn test_format_from_name() {
+        assert_eq!(Format::from_name("out.zip"), Ok(Format::Zip));
+        assert_eq!(Format::from_name("out.tar"), Ok(Format::Tar));
+        assert_eq!(Format::from_name("dir/out.TAR.GZ"), Ok(Format::TarGz));
+        assert_eq!(Format::from_name("out.tgz"), Ok(Format::TarGz));
+        assert_eq!(Format::from_name("out.tar.zst"), Ok(Format::TarZst));
+        assert!(Format::from_name("out.txt").is_err());
+    }
+
+    #[test]
+    fn test_extract_zip() {
+        let root = temp_dir();
+        let zip = build_zip(&[
+            ("./hosts/", b"", STORED),
+            ("hosts/stored.txt", b"a.example.com\n", STORED),
+            (
+                "hosts/deflated.txt",
+                &b"b.example.com\n".repeat(100),
+                DEFLATED,
+            ),
+        ]);
+
+        let result = extract(zip.as_slice(), Format::Zip, &root, MAX_SIZE);
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+        assert_eq!(
+            fs::read(root.join("hosts/stored.txt")).unwrap(),
+            b"a.example.com\n"
+        );
+        assert_eq!(
+            fs::read(root.join("hosts/deflated.txt")).unwrap(),
+            b"b.example.com\n".repeat(100)
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_extract_zip_slip() {
+        let root = temp_dir();
+        for name in ["../escaped.txt", "/etc/escaped.txt", "a/../../escaped.txt"] {
+            let zip = build_zip(&[(name, b"content", STORED)]);
+            let result = extract(zip.as_slice(), Format::Zip, &root, MAX_SIZE);
+            assert!(
+                result.is_err(),
+                "expected error for '{name}', got {result:?}"
+            );
+        }
+        assert!(!root.parent().unwrap().join("escaped.txt").exists());
+        assert!(!root.exists(), "expected created root to be removed");
+    }
+
+    #[test]
+    fn test_extract_tar_gz() {
+        let root = temp_dir();
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        let mut header = tar::Header::new_gnu();
+        header.set_size(14);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder
+            .append_data(&mut header, "./hosts/a.txt", &b"a.example.com\n"[..])
+            .unwrap();
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(tar::EntryType::Symlink);
+        header.set_size(0);
+        header.set_cksum();
+        builder
+            .append_link(&mut header, "hosts/passwd", "/etc/passwd")
+            .unwrap();
+        let tgz = builder.into_inner().unwrap().finish().unwrap();
+
+        let result = extract(tgz.as_slice(), Format::TarGz, &root, MAX_SIZE);
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+        assert_eq!(
+            fs::read(root.join("hosts/a.txt")).unwrap(),
+            b"a.example.com\n"
+        );
+        assert!(!root.join("hosts/passwd").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_extract_not_zip() {
+        let root = temp_dir();
+        let result = extract(&b"not a zip archive"[..], Format::Zip, &root, MAX_SIZE);
+        assert!(result.is_err(), "expected error, got {result:?}");
+
+        assert!(!root.exists(), "expected created root to be removed");
+    }
+
+    #[test]
+    fn test_extract_checksum_mismatch() {
+        let root = temp_dir();
+        let mut zip = build_zip(&[("a.txt", b"content", STORED)]);
+        // Corrupt the stored content
+        zip[30 + "a.txt".len()] ^= 1;
+
+        let result = extract(zip.as_slice(), Format::Zip, &root, MAX_SIZE);
+        assert!(result.is_err(), "expected error, got {result:?}");
+
+        assert!(!root.exists(), "expected created root to be removed");
+    }
+
+    #[test]
+    fn test_extract_max_size() {
+        let root = temp_dir();
+        fs::create_dir_all(root.join("hosts")).unwrap();
+        fs::write(root.join("hosts/existing.txt"), b"kept").unwrap();
+        let zip = build_zip(&[
+            ("hosts/a.txt", &[b'a'; 8], STORED),
+            ("new/b.txt", &[b'b'; 8], STORED),
+            ("new/c.txt", &[b'c'; 8], DEFLATED),
+        ]);
+
+        let result = extract(zip.as_slice(), Format::Zip, &root, 20);
+        assert!(result.is_err(), "expected error, got {result:?}");
+        assert_eq!(fs::read(root.join("hosts/existing.txt")).unwrap(), b"kept");
+        assert!(!root.join("hosts/a.txt").exists());
+        assert!(!root.join("new").exists());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,3 +1,18 @@
+mod artifact_diff;
 pub mod cli;
 pub mod client;
+mod compression;
+mod config;
+mod download_cache;
+mod encryption;
+mod expression;
+mod extract;
+mod local_run;
+mod multipart;
+mod progress;
+mod remote_zip;
+mod template;
+mod throttle;
+mod transfer;
 mod validation;
+mod workflow;
@@ -0,0 +1,287 @@
+use crate::workflow::{InputKind, Workflow};
+use crate::{expression, transfer};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+type Result<T> = std::result::Result<T, String>;
+
+/// File marking a job directory as created by a local run, so it may be emptied.
+const RUN_MARKER: &str = ".bh-run";
+
+/// Runs every job of the scan on the local machine, in dependency order.
+///
+/// Each job runs its steps with `sh -c` inside `<output>/<job>/workspace`, and its
+/// artifacts are copied to `<output>/<job>/artifacts/<name>`. The job directory
+/// is emptied before the job runs, and artifact paths must stay inside it. A job
+/// directory that exists but was not created by a local run is never deleted,
+/// the run fails instead.
+pub fn run(
+    workflow: &Workflow,
+    scan_name: &str,
+    inputs: Option<BTreeMap<String, Value>>,
+    output: &Path,
+) -> Result<()> {
+    let scan = workflow
+        .scans
+        .get(scan_name)
+        .ok_or_else(|| format!("Scan '{scan_name}' does not exist in the workflow"))?;
+    let order = workflow
+        .job_order(scan_name)
+        .ok_or_else(|| format!("Jobs of scan '{scan_name}' form a dependency cycle"))?;
+
+    let inputs = resolve_inputs(workflow, scan_name, inputs.unwrap_or_default())?;
+
+    for job_name in order {
+        let job = &scan.jobs[job_name];
+
+        let mut vars = workflow.vars.clone();
+        vars.extend(job.vars.clone());
+        let context = serde_json::json!({
+            "inputs": inputs,
+            "vars": vars,
+        });
+
+        // Start from an empty job directory so runs do not see each other's files
+        let job_dir = transfer::confined_output(output, job_name)?;
+        if job_dir.exists() {
+            if !job_dir.join(RUN_MARKER).is_file() {
+                return Err(format!(
+                    "Job directory '{}' already exists and was not created by a local run, refusing to delete it",
+                    job_dir.display()
+                ));
+            }
+            fs::remove_dir_all(&job_dir).map_err(|err| {
+                format!(
+                    "Failed to clean up job directory '{}': {err:?}",
+                    job_dir.display()
+                )
+            })?;
+        }
+        let workspace = job_dir.join("workspace");
+        fs::create_dir_all(&workspace).map_err(|err| {
+            format!(
+                "Failed to create workspace '{}': {err:?}",
+                workspace.display()
+            )
+        })?;
+        fs::write(job_dir.join(RUN_MARKER), "").map_err(|err| {
+            format!(
+                "Failed to mark job directory '{}': {err:?}",
+                job_dir.display()
+            )
+        })?;
+
+        for (i, step) in job.steps.iter().enumerate() {
+            let step_name = step.name.clone().unwrap_or_else(|| format!("#{}", i + 1));
+            eprintln!("==> {scan_name}.{job_name}: {step_name}");
+
+            let script = expression::render(&step.run, &context)
+                .map_err(|err| format!("Failed to render step '{step_name}': {err}"))?;
+
+            let status = Command::new("sh")
+                .arg("-c")
+                .arg(&script)
+                .current_dir(&workspace)
+                .status()
+                .map_err(|err| format!("Failed to start step '{step_name}': {err:?}"))?;
+
+            if !status.success() {
+                return Err(format!(
+                    "Step '{step_name}' of job '{job_name}' failed with {status}"
+                ));
+            }
+        }
+
+        if job.artifacts.is_empty() {
+            continue;
+        }
+
+        let artifacts = job_dir.join("artifacts");
+        fs::create_dir_all(&artifacts).map_err(|err| {
+            format!(
+                "Failed to create artifacts directory '{}': {err:?}",
+                artifacts.display()
+            )
+        })?;
+        for (name, path) in &job.artifacts {
+            let path = expression::render(path, &context)
+                .map_err(|err| format!("Failed to render artifact '{name}': {err}"))?;
+            let src = transfer::confined_output(&workspace, path.trim_start_matches("./"))
+                .map_err(|err| format!("Invalid path of artifact '{name}': {err}"))?;
+            let dst = transfer::confined_output(&artifacts, name)
+                .map_err(|err| format!("Invalid artifact name '{name}': {err}"))?;
+            fs::copy(src, dst).map_err(|err| {
+                format!(
+                    "Failed to store artifact '{name}' of job '{job_name}' from '{path}': {err:?}"
+                )
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks the inputs against the scan definition and fills in the defaults.
+fn resolve_inputs(
+    workflow: &Workflow,
+    scan_name: &str,
+    mut inputs: BTreeMap<String, Value>,
+) -> Result<Map<String, Value>> {
+    let definitions = &workflow.scans[scan_name].inputs;
+
+    if let Some(key) = inputs.keys().find(|k| !definitions.contains_key(*k)) {
+        return Err(format!("Scan '{scan_name}' has no input '{key}'"));
+    }
+
+    let mut resolved = Map::new();
+    for (key, definition) in definitions {
+        let value = match (inputs.remove(key), &definition.default) {
+            (Some(value), _) => value,
+            (None, Some(default)) => match definition.kind {
+                InputKind::String => Value::String(default.clone()),
+                InputKind::Bool => Value::Bool(default == "true"),
+            },
+            (None, None) if definition.required => {
+                return Err(format!("Input '{key}' is required"));
+            }
+            (None, None) => Value::Null,
+        };
+
+        match (definition.kind, &value) {
+            (InputKind::String, Value::String(_))
+            | (InputKind::Bool, Value::Bool(_))
+            | (_, Value::Null) => {}
+            (InputKind::String, _) => return Err(format!("Input '{key}' must be a string")),
+            (InputKind::Bool, _) => return Err(format!("Input '{key}' must be a boolean")),
+        }
+
+        resolved.insert(key.clone(), value);
+    }
+
+    Ok(resolved)
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::env;
+    use uuid::Uuid;
+
+    const WORKFLOW: &str = r#"vars:
+  GREETING: hello
+scans:
+  example:
+    inputs:
+      domain:
+        type: string
+        required: true
+      deep:
+        type: bool
+        default: false
+    jobs:
+      first:
+        steps:
+          - run: echo "${{ vars.GREETING }} ${{ inputs.domain }}" > out.txt
+        artifacts:
+          greeting: out.txt
+      second:
+        needs: [first]
+        vars:
+          GREETING: bye
+        steps:
+          - run: test ! -e out.txt
+          - run: echo "${{ vars.GREETING }} ${{ inputs.deep }}" > out.txt
+        artifacts:
+          greeting: out.txt
+"#;
+
+    #[test]
+    fn test_run() {
+        let workflow = Workflow::parse(WORKFLOW).expect("workflow to be valid");
+        let output = env::temp_dir().join(Uuid::now_v7().to_string());
+        let inputs = BTreeMap::from([(
+            "domain".to_string(),
+            Value::String("example.com".to_string()),
+        )]);
+
+        let result = run(&workflow, "example", Some(inputs), &output);
+        let first = fs::read_to_string(output.join("first/artifacts/greeting"));
+        let second = fs::read_to_string(output.join("second/artifacts/greeting"));
+        fs::remove_dir_all(&output).ok();
+
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+        assert_eq!(first.ok().as_deref(), Some("hello example.com\n"));
+        assert_eq!(second.ok().as_deref(), Some("bye false\n"));
+    }
+
+    #[test]
+    fn test_run_is_hermetic() {
+        let workflow = Workflow::parse(
+            "scans:\n  example:\n    jobs:\n      only:\n        steps:\n          - run: test ! -e stale.txt && touch stale.txt\n",
+        )
+        .expect("workflow to be valid");
+        let output = env::temp_dir().join(Uuid::now_v7().to_string());
+
+        let first = run(&workflow, "example", None, &output);
+        let second = run(&workflow, "example", None, &output);
+        fs::remove_dir_all(&output).ok();
+
+        assert!(first.is_ok(), "expected ok, got {first:?}");
+        assert!(second.is_ok(), "expected ok, got {second:?}");
+    }
+
+    #[test]
+    fn test_run_keeps_foreign_directory() {
+        let workflow = Workflow::parse(
+            "scans:\n  example:\n    jobs:\n      src:\n        steps:\n          - run: 'true'\n",
+        )
+        .expect("workflow to be valid");
+        let output = env::temp_dir().join(Uuid::now_v7().to_string());
+        fs::create_dir_all(output.join("src")).expect("failed to create directory");
+        fs::write(output.join("src/main.rs"), "fn main() {}").expect("failed to write file");
+
+        let result = run(&workflow, "example", None, &output);
+        let kept = output.join("src/main.rs").exists();
+        fs::remove_dir_all(&output).ok();
+
+        assert!(result.is_err(), "expected error, got ok");
+        assert!(kept, "expected existing directory to be kept");
+    }
+
+    #[test]
+    fn test_run_artifact_outside_workspace() {
+        let output = env::temp_dir().join(Uuid::now_v7().to_string());
+        for (name, path) in [("escaped", "../../escaped.txt"), ("../escaped", "out.txt")] {
+            let workflow = Workflow::parse(&format!(
+                "scans:\n  example:\n    jobs:\n      only:\n        steps:\n          - run: touch out.txt\n        artifacts:\n          {name}: {path}\n"
+            ))
+            .expect("workflow to be valid");
+
+            let result = run(&workflow, "example", None, &output);
+            assert!(result.is_err(), "expected error for {name}: {path}, got ok");
+        }
+        fs::remove_dir_all(&output).ok();
+    }
+
+    #[test]
+    fn test_run_invalid_inputs() {
+        let workflow = Workflow::parse(WORKFLOW).expect("workflow to be valid");
+        let output = env::temp_dir().join(Uuid::now_v7().to_string());
+
+        let result = run(&workflow, "example", None, &output);
+        assert!(result.is_err(), "expected missing input error, got ok");
+
+        let inputs = BTreeMap::from([("domain".to_string(), Value::Bool(true))]);
+        let result = run(&workflow, "example", Some(inputs), &output);
+        assert!(result.is_err(), "expected type error, got ok");
+
+        let inputs = BTreeMap::from([("other".to_string(), Value::Bool(true))]);
+        let result = run(&workflow, "example", Some(inputs), &output);
+        assert!(result.is_err(), "expected unknown input error, got ok");
+
+        assert!(!output.exists(), "no job should have run");
+    }
+}
@@ -0,0 +1,562 @@
+use crate::client::{Client, CompletedPart, Error, UploadOptions};
+use crate::config;
+use crate::progress::Transfer;
+use crate::transfer::{self, Connections};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use std::{fs, thread};
+
+type Result<T> = std::result::Result<T, String>;
+
+/// Files larger than this are uploaded in parts.
+pub const THRESHOLD: u64 = 256 * 1024 * 1024;
+
+const PART_SIZE: u64 = 64 * 1024 * 1024;
+const MAX_ATTEMPTS: u32 = 4;
+const RETRY_DELAY: Duration = Duration::from_millis(500);
+/// Saved states not updated for longer than this are removed, the server
+/// drops unfinished uploads well before.
+const STATE_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Progress of a multipart upload, saved after every part so that an
+/// interrupted upload can pick up where it stopped.
+#[derive(Serialize, Deserialize, Debug, Default)]
+struct State {
+    upload_id: String,
+    part_size: u64,
+    /// ETags of the uploaded parts, by part number
+    parts: BTreeMap<u32, String>,
+}
+
+/// Blob a multipart upload is stored at, and how its content was encoded.
+pub struct Destination<'a> {
+    pub path: &'a str,
+    pub options: UploadOptions,
+    /// Hash of the content before it was encoded, when it was
+    pub plaintext_hash: Option<&'a str>,
+}
+
+/// Uploads the file in parts, each taking one of the `connections`, reporting
+/// the bytes sent to `tracker`.
+///
+/// The file is sent as is, so it has to be encoded according to the destination already.
+///
+/// Progress is kept in the user's cache directory, keyed by the hash of the
+/// content before it was encoded, the encoding options and the destination, so
+/// running the same upload again after a crash only sends the missing parts.
+/// Encrypted content differs on every run, so those uploads are not resumed.
+/// When the resumed upload no longer exists on the server, it starts over.
+pub fn upload<C>(
+    client: &C,
+    file: &fs::File,
+    dst: &Destination,
+    connections: &Connections,
+    tracker: &Transfer,
+) -> Result<()>
+where
+    C: Client + Sync,
+{
+    let state_dir = config::cache_dir().map(|dir| dir.join("multipart"));
+    if let Some(state_dir) = &state_dir {
+        remove_expired(state_dir);
+    }
+    let state_path = match state_dir {
+        Some(state_dir) if !dst.options.encrypt => Some(state_dir.join(state_name(file, dst)?)),
+        _ => None,
+    };
+
+    upload_parts(
+        client,
+        file,
+        dst,
+        PART_SIZE,
+        connections,
+        state_path.as_deref(),
+        tracker,
+    )
+}
+
+fn upload_parts<C>(
+    client: &C,
+    file: &fs::File,
+    destination: &Destination,
+    part_size: u64,
+    connections: &Connections,
+    state_path: Option<&Path>,
+    tracker: &Transfer,
+) -> Result<()>
+where
+    C: Client + Sync,
+{
+    let dst = destination.path;
+    let size = file
+        .metadata()
+        .map_err(|err| format!("Failed to stat the content of '{dst}': {err:?}"))?
+        .len();
+
+    let (state, resumed) = match state_path.and_then(load_state) {
+        Some(state) if state.part_size == part_size => (state, true),
+        _ => {
+            let upload_id = client
+                .create_multipart_upload(
+                    dst,
+                    size,
+                    part_size,
+                    destination.options,
+                    destination.plaintext_hash.map(str::to_string),
+                )
+                .map_err(|err| format!("Failed to start multipart upload of '{dst}': {err:?}"))?;
+            let state = State {
+                upload_id,
+                part_size,
+                parts: BTreeMap::new(),
+            };
+            save_state(state_path, &state)?;
+            (state, false)
+        }
+    };
+
+    let part_count = size.div_ceil(part_size).max(1) as u32;
+    let part_len = |part_number: u32| {
+        let offset = (part_number as u64 - 1) * part_size;
+        (offset, part_size.min(size - offset))
+    };
+    let (pending, done): (Vec<_>, Vec<_>) =
+        (1..=part_count).partition(|n| !state.parts.contains_key(n));
+    tracker.inc(done.into_iter().map(|n| part_len(n).1).sum());
+    let upload_id = state.upload_id.clone();
+    let state = Mutex::new(state);
+    // Set when the server no longer knows the upload, e.g. after it expired
+    let missing = AtomicBool::new(false);
+    let not_found = |err: &Error| {
+        if matches!(err, Error::NotFound) {
+            missing.store(true, Ordering::SeqCst);
+        }
+    };
+
+    let result = transfer::run_parallel(pending, connections.max(), |part_number| {
+        let (offset, len) = part_len(part_number);
+        let etag = retry(|| {
+            let _connection = connections.acquire();
+            let part = transfer::Section::new(file, offset, len)
+                .map_err(|err| Error::Generic(format!("Failed to read part: {err:?}")))?;
+            client.upload_blob_part(&upload_id, part_number, Box::new(part), len)
+        })
+        .map_err(|err| {
+            not_found(&err);
+            format!("Failed to upload part {part_number} of '{dst}': {err:?}")
+        })?;
+
+        // Counted once the part is in, so retried attempts are not counted twice
+        tracker.inc(len);
+        let mut state = state.lock().expect("state lock poisoned");
+        state.parts.insert(part_number, etag);
+        save_state(state_path, &state)
+    })
+    .and_then(|()| {
+        let parts = state
+            .lock()
+            .expect("state lock poisoned")
+            .parts
+            .iter()
+            .map(|(part_number, etag)| CompletedPart {
+                part_number: *part_number,
+                etag: etag.clone(),
+            })
+            .collect();
+        client
+            .complete_multipart_upload(&upload_id, parts)
+            .map_err(|err| {
+                not_found(&err);
+                format!("Failed to complete multipart upload of '{dst}': {err:?}")
+            })
+    });
+
+    match result {
+        Err(err) if resumed && missing.load(Ordering::SeqCst) => {
+            eprintln!("{err}, starting the upload over");
+            remove_state(state_path);
+            tracker.reset();
+            upload_parts(
+                client,
+                file,
+                destination,
+                part_size,
+                connections,
+                state_path,
+                tracker,
+            )
+        }
+        Err(err) => Err(err),
+        Ok(()) => {
+            remove_state(state_path);
+            Ok(())
+        }
+    }
+}
+
+/// Calls `f` until it succeeds, backing off between attempts.
+///
+/// Authorization errors and missing resources are not retried.
+fn retry<T, F>(mut f: F) -> std::result::Result<T, Error>
+where
+    F: FnMut() -> std::result::Result<T, Error>,
+{
+    let mut attempt = 1;
+    loop {
+        match f() {
+            Err(Error::Generic(err)) if attempt < MAX_ATTEMPTS => {
+                eprintln!("Attempt {attempt} failed, retrying: {err}");
+                thread::sleep(RETRY_DELAY * 2u32.pow(attempt - 1));
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Names the saved state after what ends up in the blob, wherever the
+/// content was read from.
+fn state_name(file: &fs::File, dst: &Destination) -> Result<String> {
+    let plaintext_hash = match dst.plaintext_hash {
+        Some(plaintext_hash) => plaintext_hash.to_string(),
+        None => {
+            let mut content = transfer::Hashing::new(
+                transfer::Section::new(file, 0, u64::MAX)
+                    .map_err(|err| format!("Failed to read '{}': {err:?}", dst.path))?,
+            );
+            io::copy(&mut content, &mut io::sink())
+                .map_err(|err| format!("Failed to read '{}': {err:?}", dst.path))?;
+            content.hash()
+        }
+    };
+
+    let mut hasher = Sha256::new();
+    hasher.update(format!(
+        "{plaintext_hash}\n{:?}\n{}",
+        dst.options.encoding, dst.path
+    ));
+    Ok(format!("{:x}.json", hasher.finalize()))
+}
+
+/// Removes the saved states older than [`STATE_MAX_AGE`].
+fn remove_expired(state_dir: &Path) {
+    let Ok(entries) = fs::read_dir(state_dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let expired = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > STATE_MAX_AGE);
+        if expired {
+            fs::remove_file(entry.path()).ok();
+        }
+    }
+}
+
+fn load_state(path: &Path) -> Option<State> {
+    let content = fs::read(path).ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+fn remove_state(path: Option<&Path>) {
+    if let Some(path) = path {
+        fs::remove_file(path).ok();
+    }
+}
+
+/// Saves the state when the upload can be resumed.
+fn save_state(path: Option<&Path>, state: &State) -> Result<()> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|err| format!("Failed to create directory '{}': {err:?}", parent.display()))?;
+    }
+    let content = serde_json::to_vec(state)
+        .map_err(|err| format!("Failed to serialize upload state: {err:?}"))?;
+    fs::write(path, content)
+        .map_err(|err| format!("Failed to save upload state '{}': {err:?}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockClient;
+    use crate::compression::ContentEncoding;
+    use crate::progress::Progress;
+    use mockall::predicate::*;
+    use std::env;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use uuid::Uuid;
+
+    fn completed(parts: &[(u32, &str)]) -> Vec<CompletedPart> {
+        parts
+            .iter()
+            .map(|(part_number, etag)| CompletedPart {
+                part_number: *part_number,
+                etag: etag.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_upload_parts_retries_failed_parts() {
+        let dir = env::temp_dir().join(Uuid::now_v7().to_string());
+        fs::create_dir_all(&dir).expect("failed to create dir");
+        let path = dir.join("file");
+        let state_path = dir.join("state.json");
+        fs::write(&path, "0123456789").expect("failed to write file");
+
+        let mut client = MockClient::new();
+        client
+            .expect_create_multipart_upload()
+            .with(
+                eq("dst"),
+                eq(10),
+                eq(4),
+                eq(UploadOptions::default()),
+                eq(None),
+            )
+            .times(1)
+            .returning(|_, _, _, _, _| Ok("upload".to_string()));
+
+        let failures = AtomicUsize::new(0);
+        client
+            .expect_upload_blob_part()
+            .returning(move |_, part_number, mut reader, size| {
+                let mut content = String::new();
+                reader.read_to_string(&mut content).expect("part to read");
+                assert_eq!(content.len() as u64, size);
+                if part_number == 2 && failures.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return Err(Error::Generic("connection reset".to_string()));
+                }
+                Ok(content)
+            });
+        client
+            .expect_complete_multipart_upload()
+            .with(
+                eq("upload"),
+                eq(completed(&[(1, "0123"), (2, "4567"), (3, "89")])),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let tracker = Progress::hidden().start("dst", Some(10));
+        let file = fs::File::open(&path).expect("failed to open file");
+        let result = upload_parts(
+            &client,
+            &file,
+            &Destination {
+                path: "dst",
+                options: UploadOptions::default(),
+                plaintext_hash: None,
+            },
+            4,
+            &Connections::new(2),
+            Some(&state_path),
+            &tracker,
+        );
+        let state_left = state_path.exists();
+        fs::remove_dir_all(&dir).ok();
+
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+        assert!(!state_left, "state should be removed after completion");
+        assert_eq!(tracker.position(), 10, "retried part counted twice");
+    }
+
+    #[test]
+    fn test_upload_parts_resumes() {
+        let dir = env::temp_dir().join(Uuid::now_v7().to_string());
+        fs::create_dir_all(&dir).expect("failed to create dir");
+        let path = dir.join("file");
+        let state_path = dir.join("state.json");
+        fs::write(&path, "0123456789").expect("failed to write file");
+        save_state(
+            Some(&state_path),
+            &State {
+                upload_id: "upload".to_string(),
+                part_size: 4,
+                parts: BTreeMap::from([(1, "0123".to_string()), (3, "89".to_string())]),
+            },
+        )
+        .expect("state to save");
+
+        let mut client = MockClient::new();
+        client.expect_create_multipart_upload().never();
+        client
+            .expect_upload_blob_part()
+            .with(eq("upload"), eq(2), always(), eq(4))
+            .times(1)
+            .returning(|_, _, _, _| Ok("4567".to_string()));
+        client
+            .expect_complete_multipart_upload()
+            .with(
+                eq("upload"),
+                eq(completed(&[(1, "0123"), (2, "4567"), (3, "89")])),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let file = fs::File::open(&path).expect("failed to open file");
+        let result = upload_parts(
+            &client,
+            &file,
+            &Destination {
+                path: "dst",
+                options: UploadOptions::default(),
+                plaintext_hash: None,
+            },
+            4,
+            &Connections::new(2),
+            Some(&state_path),
+            &Progress::hidden().start("dst", Some(10)),
+        );
+        fs::remove_dir_all(&dir).ok();
+
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+    }
+
+    #[test]
+    fn test_upload_parts_restarts_missing_upload() {
+        let dir = env::temp_dir().join(Uuid::now_v7().to_string());
+        fs::create_dir_all(&dir).expect("failed to create dir");
+        let path = dir.join("file");
+        let state_path = dir.join("state.json");
+        fs::write(&path, "0123456789").expect("failed to write file");
+        save_state(
+            Some(&state_path),
+            &State {
+                upload_id: "expired".to_string(),
+                part_size: 4,
+                parts: BTreeMap::from([(1, "0123".to_string())]),
+            },
+        )
+        .expect("state to save");
+
+        let mut client = MockClient::new();
+        client
+            .expect_upload_blob_part()
+            .with(eq("expired"), always(), always(), always())
+            .returning(|_, _, _, _| Err(Error::NotFound));
+        client
+            .expect_create_multipart_upload()
+            .with(
+                eq("dst"),
+                eq(10),
+                eq(4),
+                eq(UploadOptions::default()),
+                eq(None),
+            )
+            .times(1)
+            .returning(|_, _, _, _, _| Ok("upload".to_string()));
+        client
+            .expect_upload_blob_part()
+            .with(eq("upload"), always(), always(), always())
+            .times(3)
+            .returning(|_, _, mut reader, _| {
+                let mut content = String::new();
+                reader.read_to_string(&mut content).expect("part to read");
+                Ok(content)
+            });
+        client
+            .expect_complete_multipart_upload()
+            .with(
+                eq("upload"),
+                eq(completed(&[(1, "0123"), (2, "4567"), (3, "89")])),
+            )
+            .times(1)
+            .returning(|_, _| Ok(()));
+
+        let file = fs::File::open(&path).expect("failed to open file");
+        let result = upload_parts(
+            &client,
+            &file,
+            &Destination {
+                path: "dst",
+                options: UploadOptions::default(),
+                plaintext_hash: None,
+            },
+            4,
+            &Connections::new(2),
+            Some(&state_path),
+            &Progress::hidden().start("dst", Some(10)),
+        );
+        let state_left = state_path.exists();
+        fs::remove_dir_all(&dir).ok();
+
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+        assert!(!state_left, "state should be removed after completion");
+    }
+
+    #[test]
+    fn test_state_name() {
+        let dir = env::temp_dir().join(Uuid::now_v7().to_string());
+        fs::create_dir_all(&dir).expect("failed to create dir");
+        let (first, second) = (dir.join("first"), dir.join("second"));
+        fs::write(&first, "0123456789").expect("failed to write file");
+        fs::write(&second, "0123456789").expect("failed to write file");
+        let dst = |path, encoding| Destination {
+            path,
+            options: UploadOptions {
+                encoding,
+                encrypt: false,
+            },
+            plaintext_hash: None,
+        };
+
+        let (first, second) = (
+            fs::File::open(&first).expect("failed to open file"),
+            fs::File::open(&second).expect("failed to open file"),
+        );
+        let name = state_name(&first, &dst("dst", None));
+        let same_content = state_name(&second, &dst("dst", None));
+        let other_dst = state_name(&first, &dst("other", None));
+        let compressed = state_name(&first, &dst("dst", Some(ContentEncoding::Zstd)));
+        fs::remove_dir_all(&dir).ok();
+
+        assert!(name.is_ok(), "expected ok, got {name:?}");
+        assert_eq!(name, same_content);
+        assert_ne!(name, other_dst);
+        assert_ne!(name, compressed);
+    }
+
+    #[test]
+    fn test_remove_expired() {
+        let dir = env::temp_dir().join(Uuid::now_v7().to_string());
+        fs::create_dir_all(&dir).expect("failed to create dir");
+        fs::write(dir.join("fresh.json"), "{}").expect("failed to write file");
+        fs::File::create(dir.join("expired.json"))
+            .and_then(|file| file.set_modified(std::time::UNIX_EPOCH))
+            .expect("failed to write file");
+
+        remove_expired(&dir);
+        let fresh = dir.join("fresh.json").exists();
+        let expired = dir.join("expired.json").exists();
+        fs::remove_dir_all(&dir).ok();
+
+        assert!(fresh, "expected fresh state to be kept");
+        assert!(!expired, "expected expired state to be removed");
+    }
+
+    #[test]
+    fn test_retry_stops_on_client_errors() {
+        let mut calls = 0;
+        let result: std::result::Result<(), Error> = retry(|| {
+            calls += 1;
+            Err(Error::Forbidden)
+        });
+        assert!(matches!(result, Err(Error::Forbidden)));
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,180 @@
+use indicatif::{
+    HumanBytes, HumanDuration, MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle,
+};
+use std::io::{self, IsTerminal, Read};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How often a line is logged for a running transfer when stderr is not a terminal.
+const LOG_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Bar,
+    Log,
+    Hidden,
+}
+
+/// Reports the progress of file transfers on stderr.
+///
+/// Progress bars are drawn when stderr is a terminal. Otherwise, long running
+/// transfers log a line periodically, so they can be told apart from a hang.
+#[derive(Clone)]
+pub struct Progress {
+    mode: Mode,
+    multi: MultiProgress,
+}
+
+impl Progress {
+    pub fn new(enabled: bool) -> Progress {
+        let mode = if !enabled {
+            Mode::Hidden
+        } else if io::stderr().is_terminal() {
+            Mode::Bar
+        } else {
+            Mode::Log
+        };
+        let target = match mode {
+            Mode::Bar => ProgressDrawTarget::stderr(),
+            Mode::Log | Mode::Hidden => ProgressDrawTarget::hidden(),
+        };
+
+        Progress {
+            mode,
+            multi: MultiProgress::with_draw_target(target),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn hidden() -> Progress {
+        Progress::new(false)
+    }
+
+    /// Starts tracking a transfer of `total` bytes, when the size is known upfront.
+    pub fn start(&self, label: &str, total: Option<u64>) -> Transfer {
+        let bar = match total {
+            Some(total) => ProgressBar::new(total).with_style(
+                ProgressStyle::with_template(
+                    "{msg} {wide_bar} {binary_bytes}/{binary_total_bytes} {binary_bytes_per_sec} ETA {eta}",
+                )
+                .expect("progress template to be valid"),
+            ),
+            None => ProgressBar::no_length().with_style(
+                ProgressStyle::with_template("{spinner} {msg} {binary_bytes} {binary_bytes_per_sec}")
+                    .expect("progress template to be valid"),
+            ),
+        };
+
+        Transfer {
+            bar: self.multi.add(bar.with_message(label.to_string())),
+            mode: self.mode,
+            last_log: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+/// Progress of a single transfer, shared by every reader taking part in it.
+#[derive(Clone)]
+pub struct Transfer {
+    bar: ProgressBar,
+    mode: Mode,
+    /// When the last line was logged, if any was
+    last_log: Arc<Mutex<Option<Instant>>>,
+}
+
+impl Transfer {
+    /// Wraps the reader so that every byte read from it counts as transferred.
+    pub fn wrap_read<R: Read>(&self, reader: R) -> TransferReader<R> {
+        TransferReader {
+            inner: reader,
+            transfer: self.clone(),
+        }
+    }
+
+    pub fn inc(&self, n: u64) {
+        self.bar.inc(n);
+        if self.mode != Mode::Log {
+            return;
+        }
+
+        let mut last_log = self.last_log.lock().expect("log lock poisoned");
+        let since = last_log.map_or(self.bar.elapsed(), |at| at.elapsed());
+        if since >= LOG_INTERVAL {
+            *last_log = Some(Instant::now());
+            eprintln!("{}", self.describe());
+        }
+    }
+
+    #[cfg(test)]
+    pub fn position(&self) -> u64 {
+        self.bar.position()
+    }
+
+    /// Starts counting from zero again, when the transfer has to be redone.
+    pub fn reset(&self) {
+        self.bar.set_position(0);
+    }
+
+    pub fn finish(&self) {
+        self.bar.finish_and_clear();
+        if self.mode == Mode::Log && self.last_log.lock().expect("log lock poisoned").is_some() {
+            eprintln!(
+                "{}: done, {} in {}",
+                self.bar.message(),
+                HumanBytes(self.bar.position()),
+                HumanDuration(self.bar.elapsed())
+            );
+        }
+    }
+
+    fn describe(&self) -> String {
+        let rate = HumanBytes(self.bar.per_sec() as u64);
+        match self.bar.length() {
+            Some(total) => format!(
+                "{}: {} of {}, {rate}/s, ETA {}",
+                self.bar.message(),
+                HumanBytes(self.bar.position()),
+                HumanBytes(total),
+                HumanDuration(self.bar.eta())
+            ),
+            None => format!(
+                "{}: {}, {rate}/s",
+                self.bar.message(),
+                HumanBytes(self.bar.position())
+            ),
+        }
+    }
+}
+
+pub struct TransferReader<R> {
+    inner: R,
+    transfer: Transfer,
+}
+
+impl<R: Read> Read for TransferReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.transfer.inc(n as u64);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_reader_counts_bytes() {
+        let progress = Progress::hidden();
+        let transfer = progress.start("file", Some(10));
+
+        let mut first = transfer.wrap_read(io::Cursor::new(vec![0u8; 4]));
+        let mut second = transfer.wrap_read(io::Cursor::new(vec![0u8; 6]));
+        io::copy(&mut first, &mut io::sink()).expect("copy to succeed");
+        io::copy(&mut second, &mut io::sink()).expect("copy to succeed");
+
+        assert_eq!(transfer.bar.position(), 10);
+        transfer.finish();
+        assert!(transfer.bar.is_finished());
+    }
+}
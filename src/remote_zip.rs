@@ -0,0 +1,523 @@
+use crate::client::Client;
+use flate2::Crc;
+use flate2::read::DeflateDecoder;
+use std::io::{self, Read};
+
+type Result<T> = std::result::Result<T, String>;
+
+const EOCD_SIGNATURE: u32 = 0x0605_4b50;
+const EOCD_LEN: usize = 22;
+const ZIP64_LOCATOR_SIGNATURE: u32 = 0x0706_4b50;
+const ZIP64_LOCATOR_LEN: usize = 20;
+const ZIP64_EOCD_SIGNATURE: u32 = 0x0606_4b50;
+const ZIP64_EOCD_LEN: u64 = 56;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x0201_4b50;
+const CENTRAL_HEADER_LEN: usize = 46;
+const LOCAL_HEADER_SIGNATURE: u32 = 0x0403_4b50;
+const LOCAL_HEADER_LEN: u64 = 30;
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+
+/// The end of central directory record is followed by a comment of at most
+/// this many bytes, so it always lies within the last 64 KiB of the archive.
+const MAX_COMMENT_LEN: usize = u16::MAX as usize;
+
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+const ZSTD: u16 = 93;
+
+/// A file in a zip archive, as described by the central directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub name: String,
+    pub size: u64,
+    pub compressed_size: u64,
+    /// Last modification time in the archive's local time, as `YYYY-MM-DDTHH:MM:SS`
+    pub modified: String,
+    method: u16,
+    flags: u16,
+    crc32: u32,
+    local_header_offset: u64,
+}
+
+impl Entry {
+    pub fn is_dir(&self) -> bool {
+        self.name.ends_with('/')
+    }
+}
+
+/// Lists the entries of the zip archive behind a presigned URL.
+///
+/// Only the end of the archive and its central directory are downloaded.
+pub fn list<C>(client: &C, url: &str) -> Result<Vec<Entry>>
+where
+    C: Client,
+{
+    let (tail, total) = client
+        .download_url_tail(url, (EOCD_LEN + MAX_COMMENT_LEN + ZIP64_LOCATOR_LEN) as u64)
+        .map_err(|err| format!("Failed to read end of archive: {err:?}"))?;
+    let tail_offset = total
+        .checked_sub(tail.len() as u64)
+        .ok_or_else(|| "End of archive is larger than the archive".to_string())?;
+
+    let eocd = find_eocd(&tail)?;
+    let mut entries = u64::from(u16_at(&tail, eocd + 10));
+    let mut cd_size = u64::from(u32_at(&tail, eocd + 12));
+    let mut cd_offset = u64::from(u32_at(&tail, eocd + 16));
+
+    if eocd >= ZIP64_LOCATOR_LEN
+        && u32_at(&tail, eocd - ZIP64_LOCATOR_LEN) == ZIP64_LOCATOR_SIGNATURE
+    {
+        let zip64_offset = u64_at(&tail, eocd - ZIP64_LOCATOR_LEN + 8);
+        let record = read_range(client, url, zip64_offset, ZIP64_EOCD_LEN)?;
+        if u32_at(&record, 0) != ZIP64_EOCD_SIGNATURE {
+            return Err("Invalid zip64 end of central directory record".to_string());
+        }
+        entries = u64_at(&record, 32);
+        cd_size = u64_at(&record, 40);
+        cd_offset = u64_at(&record, 48);
+    }
+
+    let cd_end = cd_offset
+        .checked_add(cd_size)
+        .filter(|end| *end <= total)
+        .ok_or_else(|| "Central directory lies outside of the archive".to_string())?;
+    let cd = if cd_offset >= tail_offset {
+        tail[(cd_offset - tail_offset) as usize..(cd_end - tail_offset) as usize].to_vec()
+    } else {
+        read_range(client, url, cd_offset, cd_size)?
+    };
+
+    parse_central_directory(&cd, entries)
+}
+
+/// Opens the content of an entry of the zip archive behind a presigned URL.
+///
+/// Only the entry's local header and data are downloaded. The content is
+/// decompressed while it is read, and checked against the entry's size and
+/// CRC-32 once the end is reached.
+pub fn open<C>(client: &C, url: &str, entry: &Entry) -> Result<Box<dyn Read + Send + Sync>>
+where
+    C: Client,
+{
+    if entry.flags & 1 != 0 {
+        return Err(format!("'{}' is encrypted", entry.name));
+    }
+
+    let header = read_range(client, url, entry.local_header_offset, LOCAL_HEADER_LEN)?;
+    if u32_at(&header, 0) != LOCAL_HEADER_SIGNATURE {
+        return Err(format!("Invalid local header for '{}'", entry.name));
+    }
+    let data_offset = entry.local_header_offset
+        + LOCAL_HEADER_LEN
+        + u64::from(u16_at(&header, 26))
+        + u64::from(u16_at(&header, 28));
+
+    let data = client
+        .download_url_range(url, data_offset, entry.compressed_size)
+        .map_err(|err| format!("Failed to download '{}': {err:?}", entry.name))?;
+    let content: Box<dyn Read + Send + Sync> = match entry.method {
+        STORED => data,
+        DEFLATED => Box::new(DeflateDecoder::new(data)),
+        ZSTD => Box::new(
+            zstd::Decoder::new(data)
+                .map_err(|err| format!("Failed to decode '{}': {err:?}", entry.name))?,
+        ),
+        method => {
+            return Err(format!(
+                "'{}' uses unsupported compression method {method}",
+                entry.name
+            ));
+        }
+    };
+
+    Ok(Box::new(Checked {
+        inner: content,
+        crc: Crc::new(),
+        read: 0,
+        size: entry.size,
+        crc32: entry.crc32,
+    }))
+}
+
+fn read_range<C>(client: &C, url: &str, offset: u64, len: u64) -> Result<Vec<u8>>
+where
+    C: Client,
+{
+    let mut buf = Vec::new();
+    client
+        .download_url_range(url, offset, len)
+        .map_err(|err| format!("Failed to download archive range: {err:?}"))?
+        .read_to_end(&mut buf)
+        .map_err(|err| format!("Failed to read archive range: {err:?}"))?;
+    if buf.len() as u64 != len {
+        return Err("Archive is truncated".to_string());
+    }
+    Ok(buf)
+}
+
+/// Finds the end of central directory record, searching backwards since the
+/// comment that follows it may contain the signature as well.
+fn find_eocd(tail: &[u8]) -> Result<usize> {
+    if tail.len() < EOCD_LEN {
+        return Err("Not a zip archive".to_string());
+    }
+
+    (0..=tail.len() - EOCD_LEN)
+        .rev()
+        .find(|&i| {
+            u32_at(tail, i) == EOCD_SIGNATURE
+                && i + EOCD_LEN + usize::from(u16_at(tail, i + 20)) <= tail.len()
+        })
+        .ok_or_else(|| "Not a zip archive".to_string())
+}
+
+fn parse_central_directory(cd: &[u8], entries: u64) -> Result<Vec<Entry>> {
+    let mut parsed = Vec::new();
+    let mut pos = 0;
+
+    for _ in 0..entries {
+        if pos + CENTRAL_HEADER_LEN > cd.len() || u32_at(cd, pos) != CENTRAL_HEADER_SIGNATURE {
+            return Err("Invalid central directory".to_string());
+        }
+
+        let name_len = usize::from(u16_at(cd, pos + 28));
+        let extra_len = usize::from(u16_at(cd, pos + 30));
+        let comment_len = usize::from(u16_at(cd, pos + 32));
+        let name_start = pos + CENTRAL_HEADER_LEN;
+        let extra_start = name_start + name_len;
+        let next = extra_start + extra_len + comment_len;
+        if next > cd.len() {
+            return Err("Invalid central directory".to_string());
+        }
+
+        let mut entry = Entry {
+            name: String::from_utf8_lossy(&cd[name_start..extra_start]).into_owned(),
+            size: u64::from(u32_at(cd, pos + 24)),
+            compressed_size: u64::from(u32_at(cd, pos + 20)),
+            modified: dos_datetime(u16_at(cd, pos + 14), u16_at(cd, pos + 12)),
+            method: u16_at(cd, pos + 10),
+            flags: u16_at(cd, pos + 8),
+            crc32: u32_at(cd, pos + 16),
+            local_header_offset: u64::from(u32_at(cd, pos + 42)),
+        };
+        apply_zip64_extra(&mut entry, &cd[extra_start..extra_start + extra_len])?;
+
+        parsed.push(entry);
+        pos = next;
+    }
+
+    Ok(parsed)
+}
+
+/// Replaces the sizes and offset that did not fit in 32 bits with their
+/// values from the zip64 extra field, which lists only those, in order.
+fn apply_zip64_extra(entry: &mut Entry, mut extra: &[u8]) -> Result<()> {
+    while extra.len() >= 4 {
+        let id = u16_at(extra, 0);
+        let len = usize::from(u16_at(extra, 2));
+        if extra.len() < 4 + len {
+            break;
+        }
+
+        if id == ZIP64_EXTRA_ID {
+            let mut values = extra[4..4 + len].chunks_exact(8).map(|v| u64_at(v, 0));
+            for field in [
+                &mut entry.size,
+                &mut entry.compressed_size,
+                &mut entry.local_header_offset,
+            ] {
+                if *field == u64::from(u32::MAX) {
+                    *field = values
+                        .next()
+                        .ok_or_else(|| format!("Invalid zip64 extra field for '{}'", entry.name))?;
+                }
+            }
+            return Ok(());
+        }
+        extra = &extra[4 + len..];
+    }
+    Ok(())
+}
+
+fn dos_datetime(date: u16, time: u16) -> String {
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        1980 + (date >> 9),
+        (date >> 5) & 0xf,
+        date & 0x1f,
+        time >> 11,
+        (time >> 5) & 0x3f,
+        (time & 0x1f) * 2
+    )
+}
+
+fn u16_at(buf: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([buf[pos], buf[pos + 1]])
+}
+
+fn u32_at(buf: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(buf[pos..pos + 4].try_into().expect("4 bytes"))
+}
+
+fn u64_at(buf: &[u8], pos: usize) -> u64 {
+    u64::from_le_bytes(buf[pos..pos + 8].try_into().expect("8 bytes"))
+}
+
+/// Fails the read that reaches the end of the content when its size or
+/// CRC-32 does not match the central directory.
+struct Checked<R> {
+    inner: R,
+    crc: Crc,
+    read: u64,
+    size: u64,
+    crc32: u32,
+}
+
+impl<R: Read> Read for Checked<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.crc.update(&buf[..n]);
+        self.read += n as u64;
+
+        if (n == 0 || self.read > self.size)
+            && (self.read != self.size || self.crc.sum() != self.crc32)
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "content does not match its size or checksum",
+            ));
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MockClient;
+    use flate2::Compression;
+    use flate2::write::DeflateEncoder;
+    use std::io::Write;
+    use std::sync::Arc;
+
+    /// Builds an archive with the files stored or deflated, followed by a comment.
+    fn build_zip(files: &[(&str, &[u8], u16)], comment: &[u8]) -> Vec<u8> {
+        let mut zip = Vec::new();
+        let mut cd = Vec::new();
+
+        for (name, content, method) in files {
+            let data = match *method {
+                DEFLATED => {
+                    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+                    encoder.write_all(content).unwrap();
+                    encoder.finish().unwrap()
+                }
+                _ => content.to_vec(),
+            };
+            let mut crc = Crc::new();
+            crc.update(content);
+            let offset = zip.len() as u32;
+
+            zip.extend(LOCAL_HEADER_SIGNATURE.to_le_bytes());
+            zip.extend([20, 0, 0, 0]);
+            zip.extend(method.to_le_bytes());
+            zip.extend([0x20, 0x5a, 0x21, 0x58]);
+            zip.extend(crc.sum().to_le_bytes());
+            zip.extend((data.len() as u32).to_le_bytes());
+            zip.extend((content.len() as u32).to_le_bytes());
+            zip.extend((name.len() as u16).to_le_bytes());
+            zip.extend(3u16.to_le_bytes());
+            zip.extend(name.as_bytes());
+            zip.extend([0, 0, 0]);
+            zip.extend(&data);
+
+            cd.extend(CENTRAL_HEADER_SIGNATURE.to_le_bytes());
+            cd.extend([20, 0, 20, 0, 0, 0]);
+            cd.extend(method.to_le_bytes());
+            cd.extend([0x20, 0x5a, 0x21, 0x58]);
+            cd.extend(crc.sum().to_le_bytes());
+            cd.extend((data.len() as u32).to_le_bytes());
+            cd.extend((content.len() as u32).to_le_bytes());
+            cd.extend((name.len() as u16).to_le_bytes());
+            cd.extend([0; 12]);
+            cd.extend(offset.to_le_bytes());
+            cd.extend(name.as_bytes());
+        }
+
+        let cd_offset = zip.len() as u32;
+        zip.extend(&cd);
+        zip.extend(EOCD_SIGNATURE.to_le_bytes());
+        zip.extend([0; 4]);
+        zip.extend((files.len() as u16).to_le_bytes());
+        zip.extend((files.len() as u16).to_le_bytes());
+        zip.extend((cd.len() as u32).to_le_bytes());
+        zip.extend(cd_offset.to_le_bytes());
+        zip.extend((comment.len() as u16).to_le_bytes());
+        zip.extend(comment);
+        zip
+    }
+
+    /// Builds the same archive as `build_zip`, with its end of central directory
+    /// pointing to a zip64 record through the zip64 locator.
+    fn build_zip64(files: &[(&str, &[u8], u16)]) -> Vec<u8> {
+        let mut zip = build_zip(files, b"");
+        let eocd = zip.split_off(zip.len() - EOCD_LEN);
+        let entries = u64::from(u16_at(&eocd, 10));
+        let cd_size = u64::from(u32_at(&eocd, 12));
+        let cd_offset = u64::from(u32_at(&eocd, 16));
+        let zip64_offset = zip.len() as u64;
+
+        zip.extend(ZIP64_EOCD_SIGNATURE.to_le_bytes());
+        zip.extend((ZIP64_EOCD_LEN - 12).to_le_bytes());
+        zip.extend([45, 0, 45, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+        zip.extend(entries.to_le_bytes());
+        zip.extend(entries.to_le_bytes());
+        zip.extend(cd_size.to_le_bytes());
+        zip.extend(cd_offset.to_le_bytes());
+
+        zip.extend(ZIP64_LOCATOR_SIGNATURE.to_le_bytes());
+        zip.extend([0; 4]);
+        zip.extend(zip64_offset.to_le_bytes());
+        zip.extend(1u32.to_le_bytes());
+
+        zip.extend(EOCD_SIGNATURE.to_le_bytes());
+        zip.extend([0; 4]);
+        zip.extend([0xff; 12]);
+        zip.extend([0; 2]);
+        zip
+    }
+
+    /// Serves the archive through range requests only.
+    fn serve(zip: Vec<u8>) -> MockClient {
+        let zip = Arc::new(zip);
+        let mut client = MockClient::new();
+
+        let tail = zip.clone();
+        client.expect_download_url_tail().returning(move |_, len| {
+            let start = tail.len().saturating_sub(len as usize);
+            Ok((tail[start..].to_vec(), tail.len() as u64))
+        });
+        client
+            .expect_download_url_range()
+            .returning(move |_, offset, len| {
+                let range = zip[offset as usize..(offset + len) as usize].to_vec();
+                Ok(Box::new(io::Cursor::new(range)))
+            });
+        client
+    }
+
+    #[test]
+    fn test_list_and_open() {
+        let zip = build_zip(
+            &[
+                ("hosts/", b"", STORED),
+                ("hosts/stored.txt", b"a.example.com\n", STORED),
+                (
+                    "hosts/deflated.txt",
+                    &b"b.example.com\n".repeat(100),
+                    DEFLATED,
+                ),
+            ],
+            b"comment with a fake PK\x05\x06 signature",
+        );
+        let client = serve(zip);
+
+        let entries = list(&client, "url").unwrap_or_else(|err| panic!("expected ok, got {err}"));
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["hosts/", "hosts/stored.txt", "hosts/deflated.txt"]
+        );
+        assert!(entries[0].is_dir());
+        assert_eq!(entries[2].size, 1400);
+        assert!(entries[2].compressed_size < entries[2].size);
+        assert_eq!(entries[1].modified, "2024-01-01T11:17:00");
+
+        for (entry, expected) in [
+            (&entries[1], b"a.example.com\n".to_vec()),
+            (&entries[2], b"b.example.com\n".repeat(100)),
+        ] {
+            let mut content = Vec::new();
+            open(&client, "url", entry)
+                .unwrap_or_else(|err| panic!("expected ok, got {err}"))
+                .read_to_end(&mut content)
+                .unwrap_or_else(|err| panic!("expected ok, got {err:?}"));
+            assert_eq!(content, expected);
+        }
+    }
+
+    #[test]
+    fn test_open_checksum_mismatch() {
+        let client = serve(build_zip(&[("a.txt", b"content", STORED)], b""));
+        let mut entry = list(&client, "url").unwrap().remove(0);
+        entry.crc32 ^= 1;
+
+        let mut content = Vec::new();
+        let result = open(&client, "url", &entry)
+            .unwrap_or_else(|err| panic!("expected ok, got {err}"))
+            .read_to_end(&mut content);
+        assert!(result.is_err(), "expected error, got {result:?}");
+    }
+
+    #[test]
+    fn test_list_not_zip() {
+        let client = serve(b"just some text that is not an archive".to_vec());
+        let result = list(&client, "url");
+        assert!(result.is_err(), "expected error, got {result:?}");
+    }
+
+    #[test]
+    fn test_list_zip64() {
+        let client = serve(build_zip64(&[
+            ("a.txt", b"a.example.com\n", STORED),
+            ("b.txt", &b"b.example.com\n".repeat(100), DEFLATED),
+        ]));
+
+        let entries = list(&client, "url").unwrap_or_else(|err| panic!("expected ok, got {err}"));
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["a.txt", "b.txt"]);
+
+        let mut content = Vec::new();
+        open(&client, "url", &entries[1])
+            .unwrap_or_else(|err| panic!("expected ok, got {err}"))
+            .read_to_end(&mut content)
+            .unwrap_or_else(|err| panic!("expected ok, got {err:?}"));
+        assert_eq!(content, b"b.example.com\n".repeat(100));
+    }
+
+    #[test]
+    fn test_list_tail_larger_than_archive() {
+        let zip = build_zip(&[("a.txt", b"content", STORED)], b"");
+        let mut client = MockClient::new();
+        client
+            .expect_download_url_tail()
+            .returning(move |_, _| Ok((zip.clone(), 10)));
+
+        let result = list(&client, "url");
+        assert!(result.is_err(), "expected error, got {result:?}");
+    }
+
+    #[test]
+    fn test_zip64_extra() {
+        let mut entry = Entry {
+            name: "big.bin".to_string(),
+            size: u64::from(u32::MAX),
+            compressed_size: 10,
+            modified: String::new(),
+            method: STORED,
+            flags: 0,
+            crc32: 0,
+            local_header_offset: u64::from(u32::MAX),
+        };
+        let mut extra = vec![0x0a, 0x00, 0x04, 0x00, 1, 2, 3, 4];
+        extra.extend(ZIP64_EXTRA_ID.to_le_bytes());
+        extra.extend(16u16.to_le_bytes());
+        extra.extend((6u64 << 30).to_le_bytes());
+        extra.extend((5u64 << 30).to_le_bytes());
+
+        apply_zip64_extra(&mut entry, &extra).unwrap();
+        assert_eq!(entry.size, 6 << 30);
+        assert_eq!(entry.compressed_size, 10);
+        assert_eq!(entry.local_header_offset, 5 << 30);
+    }
+}
@@ -0,0 +1,200 @@
+use crate::validation;
+use crate::workflow::Workflow;
+use std::collections::BTreeMap;
+
+/// Starter workflow embedded in the binary.
+///
+/// Sources contain `%{name}` placeholders for each of the template variables.
+pub struct Template {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub vars: &'static [TemplateVar],
+    source: &'static str,
+}
+
+pub struct TemplateVar {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub default: &'static str,
+}
+
+const SCAN_NAME_DESCRIPTION: &str = "Name of the scan";
+const CRON_DESCRIPTION: &str = "Cron schedule of the scan";
+
+pub const TEMPLATES: &[Template] = &[
+    Template {
+        name: "subdomains",
+        description: "Subdomain enumeration with subfinder",
+        vars: &[
+            TemplateVar {
+                name: "scan_name",
+                description: SCAN_NAME_DESCRIPTION,
+                default: "subdomains",
+            },
+            TemplateVar {
+                name: "cron",
+                description: CRON_DESCRIPTION,
+                default: "0 0 * * *",
+            },
+            TemplateVar {
+                name: "domain",
+                description: "Root domain to enumerate",
+                default: "example.com",
+            },
+        ],
+        source: include_str!("templates/subdomains.yaml"),
+    },
+    Template {
+        name: "port-scan",
+        description: "Port scan with naabu",
+        vars: &[
+            TemplateVar {
+                name: "scan_name",
+                description: SCAN_NAME_DESCRIPTION,
+                default: "ports",
+            },
+            TemplateVar {
+                name: "cron",
+                description: CRON_DESCRIPTION,
+                default: "0 0 * * 0",
+            },
+            TemplateVar {
+                name: "target",
+                description: "Host or CIDR range to scan",
+                default: "example.com",
+            },
+            TemplateVar {
+                name: "top_ports",
+                description: "Number of most common ports to scan",
+                default: "1000",
+            },
+        ],
+        source: include_str!("templates/port-scan.yaml"),
+    },
+    Template {
+        name: "http-probe",
+        description: "HTTP probing of hosts stored in blob storage with httpx",
+        vars: &[
+            TemplateVar {
+                name: "scan_name",
+                description: SCAN_NAME_DESCRIPTION,
+                default: "http_probe",
+            },
+            TemplateVar {
+                name: "cron",
+                description: CRON_DESCRIPTION,
+                default: "0 6 * * *",
+            },
+            TemplateVar {
+                name: "hosts",
+                description: "Blob path of the file listing hosts to probe",
+                default: "hosts.txt",
+            },
+        ],
+        source: include_str!("templates/http-probe.yaml"),
+    },
+];
+
+pub fn get(name: &str) -> Option<&'static Template> {
+    TEMPLATES.iter().find(|t| t.name == name)
+}
+
+impl Template {
+    /// Fills in the template variables and validates the resulting workflow.
+    ///
+    /// Variables missing from `vars` take their default values.
+    pub fn render(&self, vars: &BTreeMap<String, String>) -> Result<String, String> {
+        if let Some(unknown) = vars
+            .keys()
+            .find(|k| !self.vars.iter().any(|v| v.name == k.as_str()))
+        {
+            return Err(format!(
+                "Unknown variable '{unknown}' for template '{}'",
+                self.name
+            ));
+        }
+
+        let mut source = self.source.to_string();
+        for var in self.vars {
+            let value = vars
+                .get(var.name)
+                .map(String::as_str)
+                .unwrap_or(var.default);
+            if var.name == "scan_name" && !validation::valid_scan_name(value) {
+                return Err(format!("Invalid scan name: '{value}'"));
+            }
+            // Values other than the scan name are placed in double quoted strings
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            source = source.replace(&format!("%{{{}}}", var.name), &value);
+        }
+
+        if let Err(diagnostics) = Workflow::parse(&source) {
+            let lines = diagnostics
+                .iter()
+                .map(|d| d.to_string())
+                .collect::<Vec<_>>();
+            return Err(format!(
+                "Template '{}' produced an invalid workflow:\n{}",
+                self.name,
+                lines.join("\n")
+            ));
+        }
+
+        Ok(source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_templates_render_with_defaults() {
+        for template in TEMPLATES {
+            let result = template.render(&BTreeMap::new());
+            assert!(
+                result.is_ok(),
+                "{}: expected ok, got {result:?}",
+                template.name
+            );
+            let source = result.unwrap_or_default();
+            assert!(
+                !source.contains("%{"),
+                "{}: unfilled variable",
+                template.name
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_with_vars() {
+        let template = get("subdomains").expect("template to exist");
+        let vars = BTreeMap::from([
+            ("scan_name".to_string(), "recon".to_string()),
+            ("domain".to_string(), "target \"quoted\".com".to_string()),
+        ]);
+
+        let source = template
+            .render(&vars)
+            .unwrap_or_else(|err| panic!("expected ok, got {err}"));
+        let workflow = Workflow::parse(&source).expect("rendered workflow to be valid");
+        let scan = workflow.scans.get("recon").expect("scan to exist");
+        assert_eq!(
+            scan.inputs["domain"].default.as_deref(),
+            Some("target \"quoted\".com")
+        );
+    }
+
+    #[test]
+    fn test_render_invalid_vars() {
+        let template = get("subdomains").expect("template to exist");
+
+        let vars = BTreeMap::from([("scan_name".to_string(), "bad name".to_string())]);
+        let result = template.render(&vars);
+        assert!(result.is_err(), "expected error, got {result:?}");
+
+        let vars = BTreeMap::from([("unknown".to_string(), "value".to_string())]);
+        let result = template.render(&vars);
+        assert!(result.is_err(), "expected error, got {result:?}");
+    }
+}
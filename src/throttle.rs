@@ -0,0 +1,96 @@
+use std::io::{self, Read};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Token bucket allowing bursts of up to one second worth of bytes.
+///
+/// Clones share the same budget, so concurrent transfers are limited together.
+#[derive(Clone)]
+pub struct RateLimiter {
+    bytes_per_sec: f64,
+    state: Arc<Mutex<(f64, Instant)>>,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_sec: u64) -> RateLimiter {
+        let bytes_per_sec = bytes_per_sec.max(1) as f64;
+        RateLimiter {
+            bytes_per_sec,
+            state: Arc::new(Mutex::new((bytes_per_sec, Instant::now()))),
+        }
+    }
+
+    /// Takes `n` bytes from the bucket, sleeping until they are paid off when it runs dry.
+    fn acquire(&self, n: u64) {
+        let wait = {
+            let mut state = self.state.lock().expect("rate limiter lock poisoned");
+            let (available, last) = &mut *state;
+            let now = Instant::now();
+            *available = (*available
+                + now.duration_since(*last).as_secs_f64() * self.bytes_per_sec)
+                .min(self.bytes_per_sec);
+            *last = now;
+            *available -= n as f64;
+            if *available < 0.0 {
+                Duration::from_secs_f64(-*available / self.bytes_per_sec)
+            } else {
+                Duration::ZERO
+            }
+        };
+
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+}
+
+/// Reader slowed down to the rate of its limiter, if it has one.
+pub struct Throttled<R> {
+    inner: R,
+    limiter: Option<RateLimiter>,
+}
+
+impl<R: Read> Throttled<R> {
+    pub fn new(reader: R, limiter: Option<RateLimiter>) -> Throttled<R> {
+        Throttled {
+            inner: reader,
+            limiter,
+        }
+    }
+}
+
+impl<R: Read> Read for Throttled<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(limiter) = &self.limiter {
+            limiter.acquire(n as u64);
+        }
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limit() {
+        let limiter = RateLimiter::new(10_000);
+
+        // The first second worth of bytes is a burst, the remaining 5000 bytes
+        // shared by both readers take half a second more
+        let start = Instant::now();
+        for _ in 0..2 {
+            let mut reader =
+                Throttled::new(io::Cursor::new(vec![0u8; 7_500]), Some(limiter.clone()));
+            io::copy(&mut reader, &mut io::sink()).expect("copy to succeed");
+        }
+        let elapsed = start.elapsed();
+
+        assert!(
+            elapsed >= Duration::from_millis(400),
+            "expected throttling, took {elapsed:?}"
+        );
+    }
+}
@@ -0,0 +1,595 @@
+use crate::{config, validation};
+use glob::{MatchOptions, Pattern};
+use sha2::{Digest, Sha256};
+use std::io::{self, ErrorKind, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{fs, process, thread};
+
+type Result<T> = std::result::Result<T, String>;
+
+/// Include and exclude globs matched against `/` separated relative paths.
+///
+/// `*` does not cross directories, while `**` does.
+#[derive(Debug, Default)]
+pub struct Filter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+impl Filter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Filter> {
+        let compile = |globs: &[String]| {
+            globs
+                .iter()
+                .map(|glob| {
+                    Pattern::new(glob).map_err(|err| format!("Invalid glob '{glob}': {err}"))
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+
+        Ok(Filter {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    /// A path matches when it matches any include glob, if there are any, and no exclude glob.
+    pub fn matches(&self, path: &str) -> bool {
+        let matches = |p: &Pattern| p.matches_with(path, MATCH_OPTIONS);
+        (self.include.is_empty() || self.include.iter().any(matches))
+            && !self.exclude.iter().any(matches)
+    }
+}
+
+/// Lists files under `root` recursively, as full paths along with their
+/// `/` separated paths relative to `root`, sorted by the relative path.
+pub fn walk_dir(root: &Path) -> Result<Vec<(PathBuf, String)>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+
+    while let Some(dir) = dirs.pop() {
+        let entries = fs::read_dir(&dir)
+            .map_err(|err| format!("Failed to read directory '{}': {err:?}", dir.display()))?;
+        for entry in entries {
+            let entry = entry
+                .map_err(|err| format!("Failed to read directory '{}': {err:?}", dir.display()))?;
+            let path = entry.path();
+            let file_type = entry
+                .file_type()
+                .map_err(|err| format!("Failed to stat '{}': {err:?}", path.display()))?;
+
+            if file_type.is_dir() {
+                dirs.push(path);
+            } else if file_type.is_file() {
+                let relative = path
+                    .strip_prefix(root)
+                    .map_err(|err| format!("Failed to resolve '{}': {err:?}", path.display()))?
+                    .components()
+                    .map(|c| c.as_os_str().to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join("/");
+                files.push((path, relative));
+            }
+        }
+    }
+
+    files.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok(files)
+}
+
+/// Calls `f` for every item on up to `concurrency` threads.
+///
+/// Once an item fails no new items are started, and the first error is returned.
+pub fn run_parallel<T, F>(items: Vec<T>, concurrency: usize, f: F) -> Result<()>
+where
+    T: Send,
+    F: Fn(T) -> Result<()> + Sync,
+{
+    let workers = concurrency.max(1).min(items.len());
+    let queue = Mutex::new(items.into_iter());
+    let error = Mutex::new(None);
+
+    thread::scope(|s| {
+        for _ in 0..workers {
+            s.spawn(|| {
+                loop {
+                    if error.lock().expect("error lock poisoned").is_some() {
+                        return;
+                    }
+                    let Some(item) = queue.lock().expect("queue lock poisoned").next() else {
+                        return;
+                    };
+                    if let Err(err) = f(item) {
+                        error
+                            .lock()
+                            .expect("error lock poisoned")
+                            .get_or_insert(err);
+                        return;
+                    }
+                }
+            });
+        }
+    });
+
+    match error.into_inner().expect("error lock poisoned") {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// Caps the number of transfers running at the same time across threads,
+/// e.g. for the parts of large files uploaded in parallel with other files.
+pub struct Connections {
+    max: usize,
+    free: Mutex<usize>,
+    freed: Condvar,
+}
+
+/// Connection taken from [`Connections`], given back when dropped.
+pub struct Connection<'a>(&'a Connections);
+
+impl Connections {
+    pub fn new(max: usize) -> Connections {
+        let max = max.max(1);
+        Connections {
+            max,
+            free: Mutex::new(max),
+            freed: Condvar::new(),
+        }
+    }
+
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// Waits until a connection is free and takes it.
+    pub fn acquire(&self) -> Connection<'_> {
+        let mut free = self.free.lock().expect("connections lock poisoned");
+        while *free == 0 {
+            free = self.freed.wait(free).expect("connections lock poisoned");
+        }
+        *free -= 1;
+        Connection(self)
+    }
+}
+
+impl Drop for Connection<'_> {
+    fn drop(&mut self) {
+        *self.0.free.lock().expect("connections lock poisoned") += 1;
+        self.0.freed.notify_one();
+    }
+}
+
+/// Joins a blob prefix and a relative path with a single `/`.
+pub fn join_blob_path(prefix: &str, relative: &str) -> String {
+    let prefix = prefix.trim_end_matches('/');
+    if prefix.is_empty() {
+        relative.to_string()
+    } else {
+        format!("{prefix}/{relative}")
+    }
+}
+
+/// Turns a blob path into a directory prefix ending with `/`, unless it is empty.
+pub fn blob_dir_prefix(path: &str) -> String {
+    if path.is_empty() || path.ends_with('/') {
+        path.to_string()
+    } else {
+        format!("{path}/")
+    }
+}
+
+/// Resolves a blob path below `root`, creating its parent directories.
+///
+/// Paths that are absolute or contain `..` are rejected. Parent directories
+/// are created one level at a time, and any existing symlink along the way,
+/// including the output itself, is rejected before anything is created
+/// through it, so the output cannot be redirected outside of `root`.
+pub fn confined_output(root: &Path, blob_path: &str) -> Result<PathBuf> {
+    let relative = Path::new(blob_path);
+    if !validation::valid_blob_path(blob_path)
+        || !relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+    {
+        return Err(format!("Refusing to write unsafe blob path '{blob_path}'"));
+    }
+
+    fs::create_dir_all(root)
+        .map_err(|err| format!("Failed to create directory '{}': {err:?}", root.display()))?;
+
+    let mut output = root.to_path_buf();
+    let mut components = relative.components().peekable();
+    while let Some(component) = components.next() {
+        output.push(component);
+        let is_last = components.peek().is_none();
+
+        match fs::symlink_metadata(&output) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                return Err(format!(
+                    "Refusing to write blob path '{blob_path}' through symlink '{}'",
+                    output.display()
+                ));
+            }
+            Ok(metadata) if !is_last && !metadata.is_dir() => {
+                return Err(format!("'{}' is not a directory", output.display()));
+            }
+            Ok(_) => {}
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                if !is_last {
+                    fs::create_dir(&output).map_err(|err| {
+                        format!("Failed to create directory '{}': {err:?}", output.display())
+                    })?;
+                }
+            }
+            Err(err) => {
+                return Err(format!("Failed to stat '{}': {err:?}", output.display()));
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+/// Hex encoded SHA-256 of the file content, as reported for blobs by the API.
+/// Reader computing the SHA-256 hash of the content read through it.
+pub struct Hashing<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> Hashing<R> {
+    pub fn new(reader: R) -> Hashing<R> {
+        Hashing {
+            inner: reader,
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// Hex encoded hash of the content read so far.
+    pub fn hash(self) -> String {
+        format!("{:x}", self.hasher.finalize())
+    }
+}
+
+impl<R: Read> Read for Hashing<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)
+        .map_err(|err| format!("Failed to open file '{}': {err:?}", path.display()))?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)
+        .map_err(|err| format!("Failed to read file '{}': {err:?}", path.display()))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Reports whether the local file differs from content with the given hash,
+/// and size when it is known.
+///
+/// The file is only hashed when the sizes match.
+pub fn file_differs(path: &Path, size: Option<u64>, hash: &str) -> Result<bool> {
+    let metadata = fs::metadata(path)
+        .map_err(|err| format!("Failed to stat '{}': {err:?}", path.display()))?;
+    if size.is_some_and(|size| metadata.len() != size) {
+        return Ok(true);
+    }
+    Ok(!sha256_file(path)?.eq_ignore_ascii_case(hash))
+}
+
+/// Spool files left behind for longer than this are removed by later runs.
+const STALE_SPOOL_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Content copied to a file, so it can be uploaded with a known size.
+///
+/// The file is created in the user's cache directory, only readable by the
+/// current user. On Unix it is unlinked as soon as it is created, so its
+/// content goes away with the process even when it is interrupted. Elsewhere
+/// it is removed when dropped, and files that interrupted runs left behind
+/// are removed by later runs.
+pub struct Spool {
+    file: fs::File,
+    path: PathBuf,
+}
+
+impl Spool {
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Spool> {
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let dir = config::cache_dir()
+            .ok_or_else(|| "Failed to find a cache directory, set XDG_CACHE_HOME".to_string())?
+            .join("spool");
+        config::create_private_dir(&dir)
+            .map_err(|err| format!("Failed to create directory '{}': {err:?}", dir.display()))?;
+        remove_stale(&dir);
+
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let path = dir.join(format!(
+            "{}-{nanos}-{}",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let mut options = fs::OpenOptions::new();
+        options.read(true).write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let file = options
+            .open(&path)
+            .map_err(|err| format!("Failed to create '{}': {err:?}", path.display()))?;
+        #[cfg(unix)]
+        fs::remove_file(&path)
+            .map_err(|err| format!("Failed to unlink '{}': {err:?}", path.display()))?;
+
+        let mut spool = Spool { file, path };
+        io::copy(reader, &mut spool.file)
+            .map_err(|err| format!("Failed to write '{}': {err:?}", spool.path.display()))?;
+
+        Ok(spool)
+    }
+
+    pub fn file(&self) -> &fs::File {
+        &self.file
+    }
+}
+
+impl Drop for Spool {
+    fn drop(&mut self) {
+        #[cfg(not(unix))]
+        fs::remove_file(&self.path).ok();
+    }
+}
+
+/// Removes the spool files older than [`STALE_SPOOL_AGE`].
+fn remove_stale(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let stale = entry
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > STALE_SPOOL_AGE);
+        if stale {
+            fs::remove_file(entry.path()).ok();
+        }
+    }
+}
+
+/// Reader of `len` bytes of a file from `offset` on.
+///
+/// Reads do not move the position the file shares with its clones, so
+/// sections of the same file can be read at the same time.
+pub struct Section {
+    file: fs::File,
+    offset: u64,
+    remaining: u64,
+}
+
+impl Section {
+    pub fn new(file: &fs::File, offset: u64, len: u64) -> io::Result<Section> {
+        Ok(Section {
+            file: file.try_clone()?,
+            offset,
+            remaining: len,
+        })
+    }
+}
+
+impl Read for Section {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let max = buf
+            .len()
+            .min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+        if max == 0 {
+            return Ok(0);
+        }
+        #[cfg(unix)]
+        let n = std::os::unix::fs::FileExt::read_at(&self.file, &mut buf[..max], self.offset)?;
+        #[cfg(windows)]
+        let n = std::os::windows::fs::FileExt::seek_read(&self.file, &mut buf[..max], self.offset)?;
+        self.offset += n as u64;
+        self.remaining -= n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use uuid::Uuid;
+
+    #[test]
+    fn test_filter() {
+        let filter = Filter::new(
+            &["**/*.txt".to_string(), "*.json".to_string()],
+            &["tmp/**".to_string()],
+        )
+        .expect("globs to be valid");
+
+        assert!(filter.matches("a.txt"));
+        assert!(filter.matches("nested/dir/a.txt"));
+        assert!(filter.matches("a.json"));
+        assert!(!filter.matches("nested/a.json"));
+        assert!(!filter.matches("tmp/a.txt"));
+        assert!(!filter.matches("a.bin"));
+
+        assert!(Filter::default().matches("anything/at/all"));
+        assert!(Filter::new(&["[".to_string()], &[]).is_err());
+    }
+
+    #[test]
+    fn test_walk_dir() {
+        let root = env::temp_dir().join(Uuid::now_v7().to_string());
+        fs::create_dir_all(root.join("b/c")).expect("failed to create dirs");
+        fs::write(root.join("a.txt"), "a").expect("failed to write file");
+        fs::write(root.join("b/c/d.txt"), "d").expect("failed to write file");
+
+        let files = walk_dir(&root);
+        fs::remove_dir_all(&root).ok();
+
+        let files = files.unwrap_or_else(|err| panic!("expected ok, got {err}"));
+        let relative = files.iter().map(|(_, r)| r.as_str()).collect::<Vec<_>>();
+        assert_eq!(relative, vec!["a.txt", "b/c/d.txt"]);
+    }
+
+    #[test]
+    fn test_run_parallel() {
+        let count = AtomicUsize::new(0);
+        let result = run_parallel((0..10).collect(), 3, |_| {
+            count.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        });
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+        assert_eq!(count.load(Ordering::SeqCst), 10);
+
+        let result = run_parallel((0..10).collect(), 3, |i| {
+            if i == 5 {
+                Err("failed".to_string())
+            } else {
+                Ok(())
+            }
+        });
+        assert_eq!(result, Err("failed".to_string()));
+    }
+
+    #[test]
+    fn test_file_differs() {
+        let path = env::temp_dir().join(Uuid::now_v7().to_string());
+        fs::write(&path, "hello").expect("failed to write file");
+        let hash = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+        let same = file_differs(&path, Some(5), hash);
+        let unknown_size = file_differs(&path, None, hash);
+        let other_size = file_differs(&path, Some(6), hash);
+        let other_hash = file_differs(&path, Some(5), &hash.replace('2', "3"));
+        fs::remove_file(&path).ok();
+
+        assert_eq!(same, Ok(false));
+        assert_eq!(unknown_size, Ok(false));
+        assert_eq!(other_size, Ok(true));
+        assert_eq!(other_hash, Ok(true));
+    }
+
+    #[test]
+    fn test_confined_output() {
+        let root = env::temp_dir().join(Uuid::now_v7().to_string());
+        fs::create_dir_all(&root).expect("failed to create dir");
+
+        let nested = confined_output(&root, "a/b/c.txt");
+        let created = root.join("a/b").is_dir();
+        let unsafe_paths = [
+            "../c.txt",
+            "a/../../c.txt",
+            "/etc/passwd",
+            "a//b",
+            "./a",
+            "",
+        ]
+        .map(|path| confined_output(&root, path));
+        #[cfg(unix)]
+        let outside = env::temp_dir().join(Uuid::now_v7().to_string());
+        #[cfg(unix)]
+        let (through_symlink, created_outside, symlinked_file) = {
+            fs::create_dir_all(&outside).expect("failed to create dir");
+            std::os::unix::fs::symlink(&outside, root.join("link"))
+                .expect("failed to create symlink");
+            let through_symlink = confined_output(&root, "link/new/c.txt");
+            let created_outside = outside.join("new").exists();
+
+            std::os::unix::fs::symlink(outside.join("target.txt"), root.join("a/b/file.txt"))
+                .expect("failed to create symlink");
+            let symlinked_file = confined_output(&root, "a/b/file.txt");
+            (through_symlink, created_outside, symlinked_file)
+        };
+        fs::remove_dir_all(&root).ok();
+        #[cfg(unix)]
+        fs::remove_dir_all(&outside).ok();
+
+        assert_eq!(nested, Ok(root.join("a/b/c.txt")));
+        assert!(created, "parent directories should be created");
+        for result in unsafe_paths {
+            assert!(result.is_err(), "expected error, got {result:?}");
+        }
+        #[cfg(unix)]
+        {
+            assert!(
+                through_symlink.is_err(),
+                "expected error, got {through_symlink:?}"
+            );
+            assert!(!created_outside, "directories created through symlink");
+            assert!(
+                symlinked_file.is_err(),
+                "expected error, got {symlinked_file:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_connections() {
+        let connections = Connections::new(2);
+        let active = AtomicUsize::new(0);
+        let peak = AtomicUsize::new(0);
+
+        let result = run_parallel((0..4).collect(), 4, |_| {
+            run_parallel((0..4).collect(), 4, |_| {
+                let _connection = connections.acquire();
+                let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                thread::sleep(Duration::from_millis(5));
+                active.fetch_sub(1, Ordering::SeqCst);
+                Ok(())
+            })
+        });
+
+        assert!(result.is_ok(), "expected ok, got {result:?}");
+        assert_eq!(peak.load(Ordering::SeqCst), 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_spool() {
+        let spool = Spool::from_reader(&mut &b"hello"[..]).expect("spool to be created");
+
+        let mut content = String::new();
+        Section::new(spool.file(), 1, 3)
+            .and_then(|mut section| section.read_to_string(&mut content))
+            .expect("spool to read");
+        assert_eq!(content, "ell");
+        assert!(!spool.path.exists(), "expected the spool to be unlinked");
+    }
+
+    #[test]
+    fn test_blob_dir_prefix() {
+        assert_eq!(blob_dir_prefix(""), "");
+        assert_eq!(blob_dir_prefix("dir"), "dir/");
+        assert_eq!(blob_dir_prefix("dir/"), "dir/");
+    }
+
+    #[test]
+    fn test_join_blob_path() {
+        assert_eq!(join_blob_path("", "a.txt"), "a.txt");
+        assert_eq!(join_blob_path("dir", "a.txt"), "dir/a.txt");
+        assert_eq!(join_blob_path("dir/", "b/a.txt"), "dir/b/a.txt");
+    }
+}
@@ -14,3 +14,15 @@ pub fn valid_workflow_var_key(s: &str) -> bool {
     s.chars()
         .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
 }
+
+/// Blob paths are relative, `/` separated paths without empty, `.` or `..` segments.
+pub fn valid_blob_path(s: &str) -> bool {
+    if s.is_empty() {
+        return false;
+    }
+
+    !s.contains('\\')
+        && !s.chars().any(char::is_control)
+        && s.split('/')
+            .all(|segment| !matches!(segment, "" | "." | ".."))
+}
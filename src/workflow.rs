@@ -0,0 +1,855 @@
+use crate::{expression, validation};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use yaml_rust2::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+
+/// Line and column in a workflow file, both starting at 1.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub col: usize,
+}
+
+impl From<Marker> for Position {
+    fn from(marker: Marker) -> Self {
+        Position {
+            line: marker.line(),
+            col: marker.col() + 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub position: Position,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.position.line, self.position.col, self.message
+        )
+    }
+}
+
+/// Workflow definition as written in the workflow YAML file.
+///
+/// ```yaml
+/// vars:
+///   MODE: full
+/// scans:
+///   subdomains:
+///     cron: "0 0 * * *"
+///     inputs:
+///       domain:
+///         type: string
+///         required: true
+///     jobs:
+///       enumerate:
+///         steps:
+///           - run: subfinder -d ${{ inputs.domain }} -o subdomains.txt
+///         artifacts:
+///           subdomains: subdomains.txt
+///       probe:
+///         needs: [enumerate]
+///         steps:
+///           - run: httpx -l subdomains.txt
+/// ```
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Workflow {
+    pub vars: BTreeMap<String, String>,
+    pub scans: BTreeMap<String, Scan>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Scan {
+    pub position: Position,
+    pub cron: Option<String>,
+    pub inputs: BTreeMap<String, Input>,
+    pub jobs: BTreeMap<String, Job>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Input {
+    pub position: Position,
+    pub kind: InputKind,
+    pub required: bool,
+    pub default: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InputKind {
+    #[default]
+    String,
+    Bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Job {
+    pub position: Position,
+    pub needs: Vec<String>,
+    pub vars: BTreeMap<String, String>,
+    pub steps: Vec<Step>,
+    pub artifacts: BTreeMap<String, String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Step {
+    pub position: Position,
+    pub name: Option<String>,
+    pub run: String,
+}
+
+impl Workflow {
+    /// Parses and validates a workflow, returning every problem found.
+    pub fn parse(source: &str) -> Result<Workflow, Vec<Diagnostic>> {
+        let (workflow, diagnostics) = Workflow::load(source);
+        if diagnostics.is_empty() {
+            Ok(workflow)
+        } else {
+            Err(diagnostics)
+        }
+    }
+
+    /// Parses as much of the workflow as possible, along with every problem found.
+    pub fn load(source: &str) -> (Workflow, Vec<Diagnostic>) {
+        let mut builder = TreeBuilder::default();
+        if let Err(err) = Parser::new_from_str(source).load(&mut builder, false) {
+            let diagnostic = Diagnostic {
+                position: (*err.marker()).into(),
+                message: err.info().to_string(),
+            };
+            return (Workflow::default(), vec![diagnostic]);
+        }
+
+        let root = builder.root.unwrap_or(Node {
+            value: Value::Scalar(String::new(), true),
+            position: Position { line: 1, col: 1 },
+        });
+
+        let mut loader = Loader::default();
+        loader.check_expressions(&root);
+        let workflow = loader.workflow(&root);
+
+        loader
+            .diagnostics
+            .sort_by_key(|d| (d.position.line, d.position.col));
+        (workflow, loader.diagnostics)
+    }
+
+    /// Returns jobs of the scan ordered so that every job comes after the jobs it needs.
+    ///
+    /// Returns `None` if the scan does not exist or its jobs form a cycle.
+    pub fn job_order(&self, scan_name: &str) -> Option<Vec<&str>> {
+        let scan = self.scans.get(scan_name)?;
+        let mut order = Vec::with_capacity(scan.jobs.len());
+        let mut done = BTreeSet::new();
+
+        while order.len() < scan.jobs.len() {
+            let ready = scan
+                .jobs
+                .iter()
+                .filter(|(name, job)| {
+                    !done.contains(name.as_str())
+                        && job.needs.iter().all(|need| done.contains(need.as_str()))
+                })
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>();
+            if ready.is_empty() {
+                return None;
+            }
+            done.extend(ready.iter().copied());
+            order.extend(ready);
+        }
+
+        Some(order)
+    }
+
+    /// Lists scans, jobs and inputs that are added or removed in `other`.
+    pub fn structural_changes(&self, other: &Workflow) -> Vec<String> {
+        fn compare(
+            changes: &mut Vec<String>,
+            kind: &str,
+            from: impl Iterator<Item = String>,
+            to: impl Iterator<Item = String>,
+        ) {
+            let from = from.collect::<BTreeSet<_>>();
+            let to = to.collect::<BTreeSet<_>>();
+            changes.extend(from.difference(&to).map(|name| format!("- {kind} {name}")));
+            changes.extend(to.difference(&from).map(|name| format!("+ {kind} {name}")));
+        }
+
+        let jobs = |w: &Workflow| {
+            w.scans
+                .iter()
+                .flat_map(|(scan, s)| s.jobs.keys().map(move |job| format!("{scan}.{job}")))
+                .collect::<Vec<_>>()
+        };
+        let inputs = |w: &Workflow| {
+            w.scans
+                .iter()
+                .flat_map(|(scan, s)| s.inputs.keys().map(move |input| format!("{scan}.{input}")))
+                .collect::<Vec<_>>()
+        };
+
+        let mut changes = Vec::new();
+        compare(
+            &mut changes,
+            "scan",
+            self.scans.keys().cloned(),
+            other.scans.keys().cloned(),
+        );
+        compare(
+            &mut changes,
+            "job",
+            jobs(self).into_iter(),
+            jobs(other).into_iter(),
+        );
+        compare(
+            &mut changes,
+            "input",
+            inputs(self).into_iter(),
+            inputs(other).into_iter(),
+        );
+        changes
+    }
+
+    /// Renders the job dependency graph in Graphviz DOT format, one cluster per scan.
+    pub fn to_dot(&self) -> String {
+        let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+
+        let mut out = String::from("digraph workflow {\n");
+        for (scan_name, scan) in &self.scans {
+            out.push_str(&format!(
+                "  subgraph {} {{\n    label={};\n",
+                quote(&format!("cluster_{scan_name}")),
+                quote(scan_name)
+            ));
+            for job_name in scan.jobs.keys() {
+                out.push_str(&format!(
+                    "    {} [label={}];\n",
+                    quote(&format!("{scan_name}.{job_name}")),
+                    quote(job_name)
+                ));
+            }
+            out.push_str("  }\n");
+        }
+        for (scan_name, scan) in &self.scans {
+            for (job_name, job) in &scan.jobs {
+                for need in &job.needs {
+                    out.push_str(&format!(
+                        "  {} -> {};\n",
+                        quote(&format!("{scan_name}.{need}")),
+                        quote(&format!("{scan_name}.{job_name}"))
+                    ));
+                }
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Renders the job dependency graph as a Mermaid flowchart, one subgraph per scan.
+    pub fn to_mermaid(&self) -> String {
+        let label = |s: &str| format!("[\"{}\"]", s.replace('"', "#quot;"));
+
+        // Job names are not restricted, so nodes get generated IDs
+        let mut ids = BTreeMap::new();
+        let mut out = String::from("flowchart LR\n");
+        for (i, (scan_name, scan)) in self.scans.iter().enumerate() {
+            out.push_str(&format!("  subgraph s{i} {}\n", label(scan_name)));
+            for job_name in scan.jobs.keys() {
+                let id = format!("n{}", ids.len());
+                out.push_str(&format!("    {id}{}\n", label(job_name)));
+                ids.insert((scan_name.as_str(), job_name.as_str()), id);
+            }
+            out.push_str("  end\n");
+        }
+        for (scan_name, scan) in &self.scans {
+            for (job_name, job) in &scan.jobs {
+                for need in &job.needs {
+                    out.push_str(&format!(
+                        "  {} --> {}\n",
+                        ids[&(scan_name.as_str(), need.as_str())],
+                        ids[&(scan_name.as_str(), job_name.as_str())]
+                    ));
+                }
+            }
+        }
+        out
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Value {
+    /// Scalar value and whether it was written without quotes
+    Scalar(String, bool),
+    Sequence(Vec<Node>),
+    Mapping(Vec<(Node, Node)>),
+}
+
+#[derive(Clone, Debug)]
+struct Node {
+    value: Value,
+    position: Position,
+}
+
+impl Node {
+    fn is_null(&self) -> bool {
+        matches!(&self.value, Value::Scalar(s, true) if s.is_empty() || s == "~" || s == "null")
+    }
+}
+
+struct OpenNode {
+    mapping: bool,
+    children: Vec<Node>,
+    position: Position,
+    anchor: usize,
+}
+
+#[derive(Default)]
+struct TreeBuilder {
+    open: Vec<OpenNode>,
+    anchors: BTreeMap<usize, Node>,
+    root: Option<Node>,
+}
+
+impl MarkedEventReceiver for TreeBuilder {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        let (node, anchor) = match ev {
+            Event::Scalar(value, style, anchor, _) => (
+                Node {
+                    value: Value::Scalar(value, style == TScalarStyle::Plain),
+                    position: mark.into(),
+                },
+                anchor,
+            ),
+            Event::Alias(anchor) => match self.anchors.get(&anchor) {
+                Some(node) => (node.clone(), 0),
+                None => return,
+            },
+            Event::SequenceStart(anchor, _) | Event::MappingStart(anchor, _) => {
+                self.open.push(OpenNode {
+                    mapping: matches!(ev, Event::MappingStart(..)),
+                    children: Vec::new(),
+                    position: mark.into(),
+                    anchor,
+                });
+                return;
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                let Some(open) = self.open.pop() else {
+                    return;
+                };
+                let value = if open.mapping {
+                    let mut children = open.children.into_iter();
+                    let mut pairs = Vec::new();
+                    while let (Some(k), Some(v)) = (children.next(), children.next()) {
+                        pairs.push((k, v));
+                    }
+                    Value::Mapping(pairs)
+                } else {
+                    Value::Sequence(open.children)
+                };
+                (
+                    Node {
+                        value,
+                        position: open.position,
+                    },
+                    open.anchor,
+                )
+            }
+            _ => return,
+        };
+
+        if anchor != 0 {
+            self.anchors.insert(anchor, node.clone());
+        }
+
+        match self.open.last_mut() {
+            Some(open) => open.children.push(node),
+            None => self.root = Some(node),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Loader {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Loader {
+    fn error(&mut self, position: Position, message: String) {
+        self.diagnostics.push(Diagnostic { position, message });
+    }
+
+    fn check_expressions(&mut self, node: &Node) {
+        match &node.value {
+            Value::Scalar(s, _) => {
+                if let Err(err) = expression::parse_template(s) {
+                    self.error(node.position, err);
+                }
+            }
+            Value::Sequence(items) => items.iter().for_each(|n| self.check_expressions(n)),
+            Value::Mapping(pairs) => pairs.iter().for_each(|(_, v)| self.check_expressions(v)),
+        }
+    }
+
+    /// Returns entries of a mapping node, reporting non-string and duplicate keys.
+    fn mapping<'a>(&mut self, node: &'a Node, what: &str) -> Vec<(&'a str, &'a Node, &'a Node)> {
+        if node.is_null() {
+            return Vec::new();
+        }
+
+        let Value::Mapping(pairs) = &node.value else {
+            self.error(node.position, format!("Expected {what} to be a mapping"));
+            return Vec::new();
+        };
+
+        let mut seen = BTreeSet::new();
+        let mut entries = Vec::with_capacity(pairs.len());
+        for (k, v) in pairs {
+            let Value::Scalar(key, _) = &k.value else {
+                self.error(k.position, format!("Expected keys of {what} to be strings"));
+                continue;
+            };
+            if !seen.insert(key.as_str()) {
+                self.error(k.position, format!("Duplicate key '{key}' in {what}"));
+                continue;
+            }
+            entries.push((key.as_str(), k, v));
+        }
+        entries
+    }
+
+    /// Returns entries of an object with a fixed set of keys, reporting unknown keys.
+    fn object<'a>(
+        &mut self,
+        node: &'a Node,
+        what: &str,
+        known: &[&str],
+    ) -> BTreeMap<&'a str, &'a Node> {
+        let mut fields = BTreeMap::new();
+        for (key, k, v) in self.mapping(node, what) {
+            if known.contains(&key) {
+                fields.insert(key, v);
+            } else {
+                self.error(k.position, format!("Unknown key '{key}' in {what}"));
+            }
+        }
+        fields
+    }
+
+    fn scalar(&mut self, node: &Node, what: &str) -> Option<String> {
+        match &node.value {
+            Value::Scalar(s, _) => Some(s.clone()),
+            _ => {
+                self.error(node.position, format!("Expected {what} to be a scalar"));
+                None
+            }
+        }
+    }
+
+    fn bool(&mut self, node: &Node, what: &str) -> bool {
+        match &node.value {
+            Value::Scalar(s, true) if s == "true" => true,
+            Value::Scalar(s, true) if s == "false" => false,
+            _ => {
+                self.error(node.position, format!("Expected {what} to be a boolean"));
+                false
+            }
+        }
+    }
+
+    fn vars(&mut self, node: &Node, what: &str) -> BTreeMap<String, String> {
+        let mut vars = BTreeMap::new();
+        for (key, k, v) in self.mapping(node, what) {
+            if !validation::valid_workflow_var_key(key) {
+                self.error(
+                    k.position,
+                    format!("Variable key '{key}' is in invalid format"),
+                );
+            }
+            if let Some(value) = self.scalar(v, &format!("variable '{key}'")) {
+                vars.insert(key.to_string(), value);
+            }
+        }
+        vars
+    }
+
+    fn workflow(&mut self, root: &Node) -> Workflow {
+        let fields = self.object(root, "workflow", &["vars", "scans"]);
+        let mut workflow = Workflow::default();
+
+        if let Some(vars) = fields.get("vars") {
+            workflow.vars = self.vars(vars, "vars");
+        }
+
+        match fields.get("scans") {
+            Some(scans) => {
+                for (name, k, v) in self.mapping(scans, "scans") {
+                    if !validation::valid_scan_name(name) {
+                        self.error(k.position, format!("Invalid scan name: '{name}'"));
+                    }
+                    let scan = self.scan(name, k.position, v);
+                    workflow.scans.insert(name.to_string(), scan);
+                }
+                if workflow.scans.is_empty() {
+                    self.error(scans.position, "Workflow has no scans".to_string());
+                }
+            }
+            None => self.error(root.position, "Missing required key 'scans'".to_string()),
+        }
+
+        workflow
+    }
+
+    fn scan(&mut self, name: &str, position: Position, node: &Node) -> Scan {
+        let what = format!("scan '{name}'");
+        let fields = self.object(node, &what, &["cron", "inputs", "jobs"]);
+        let mut scan = Scan {
+            position,
+            ..Default::default()
+        };
+
+        if let Some(cron) = fields.get("cron") {
+            scan.cron = self.scalar(cron, "cron");
+        }
+
+        if let Some(inputs) = fields.get("inputs") {
+            for (key, k, v) in self.mapping(inputs, &format!("inputs of {what}")) {
+                if !validation::valid_workflow_var_key(key) {
+                    self.error(
+                        k.position,
+                        format!("Input key '{key}' is in invalid format"),
+                    );
+                }
+                let input = self.input(key, k.position, v);
+                scan.inputs.insert(key.to_string(), input);
+            }
+        }
+
+        let Some(jobs) = fields.get("jobs") else {
+            self.error(position, format!("Missing required key 'jobs' in {what}"));
+            return scan;
+        };
+
+        let entries = self.mapping(jobs, &format!("jobs of {what}"));
+        if entries.is_empty() {
+            self.error(jobs.position, format!("Scan '{name}' has no jobs"));
+        }
+        let job_names = entries.iter().map(|(n, _, _)| *n).collect::<BTreeSet<_>>();
+        for (job_name, k, v) in entries {
+            let job = self.job(job_name, k.position, v, &job_names);
+            scan.jobs.insert(job_name.to_string(), job);
+        }
+
+        self.check_cycles(name, &scan);
+
+        scan
+    }
+
+    fn input(&mut self, key: &str, position: Position, node: &Node) -> Input {
+        let what = format!("input '{key}'");
+        let fields = self.object(node, &what, &["type", "required", "default", "description"]);
+        let mut input = Input {
+            position,
+            ..Default::default()
+        };
+
+        if let Some(kind) = fields.get("type") {
+            match self.scalar(kind, "type").as_deref() {
+                Some("string") => input.kind = InputKind::String,
+                Some("bool") => input.kind = InputKind::Bool,
+                Some(other) => self.error(
+                    kind.position,
+                    format!("Unknown type '{other}' of {what}, expected 'string' or 'bool'"),
+                ),
+                None => {}
+            }
+        }
+        if let Some(required) = fields.get("required") {
+            input.required = self.bool(required, "required");
+        }
+        if let Some(default) = fields.get("default") {
+            if input.kind == InputKind::Bool {
+                self.bool(default, "default");
+            }
+            input.default = self.scalar(default, "default");
+        }
+        if let Some(description) = fields.get("description") {
+            input.description = self.scalar(description, "description");
+        }
+
+        input
+    }
+
+    fn job(
+        &mut self,
+        name: &str,
+        position: Position,
+        node: &Node,
+        job_names: &BTreeSet<&str>,
+    ) -> Job {
+        let what = format!("job '{name}'");
+        let fields = self.object(node, &what, &["needs", "vars", "steps", "artifacts"]);
+        let mut job = Job {
+            position,
+            ..Default::default()
+        };
+
+        if let Some(needs) = fields.get("needs") {
+            let items = match &needs.value {
+                Value::Sequence(items) => items.iter().collect(),
+                _ => vec![*needs],
+            };
+            for item in items {
+                let Some(need) = self.scalar(item, "needs") else {
+                    continue;
+                };
+                if need == name {
+                    self.error(item.position, format!("Job '{name}' cannot need itself"));
+                } else if !job_names.contains(need.as_str()) {
+                    self.error(
+                        item.position,
+                        format!("Job '{name}' needs unknown job '{need}'"),
+                    );
+                } else {
+                    job.needs.push(need);
+                }
+            }
+        }
+
+        if let Some(vars) = fields.get("vars") {
+            job.vars = self.vars(vars, &format!("vars of {what}"));
+        }
+
+        match fields.get("steps").map(|steps| &steps.value) {
+            Some(Value::Sequence(steps)) if !steps.is_empty() => {
+                for step in steps {
+                    let fields = self.object(step, "step", &["name", "run"]);
+                    let mut s = Step {
+                        position: step.position,
+                        ..Default::default()
+                    };
+                    if let Some(name) = fields.get("name") {
+                        s.name = self.scalar(name, "name");
+                    }
+                    match fields.get("run") {
+                        Some(run) => s.run = self.scalar(run, "run").unwrap_or_default(),
+                        None => self.error(step.position, "Missing required key 'run'".into()),
+                    }
+                    job.steps.push(s);
+                }
+            }
+            Some(_) => self.error(
+                fields["steps"].position,
+                format!("Expected steps of {what} to be a non-empty sequence"),
+            ),
+            None => self.error(position, format!("Missing required key 'steps' in {what}")),
+        }
+
+        if let Some(artifacts) = fields.get("artifacts") {
+            for (artifact, _, v) in self.mapping(artifacts, &format!("artifacts of {what}")) {
+                if let Some(path) = self.scalar(v, &format!("artifact '{artifact}'")) {
+                    job.artifacts.insert(artifact.to_string(), path);
+                }
+            }
+        }
+
+        job
+    }
+
+    fn check_cycles(&mut self, scan_name: &str, scan: &Scan) {
+        fn visit<'a>(
+            scan: &'a Scan,
+            job: &'a str,
+            path: &mut Vec<&'a str>,
+            done: &mut BTreeSet<&'a str>,
+        ) -> Option<Vec<&'a str>> {
+            if let Some(start) = path.iter().position(|j| *j == job) {
+                let mut cycle = path[start..].to_vec();
+                cycle.push(job);
+                return Some(cycle);
+            }
+            if done.contains(job) {
+                return None;
+            }
+            path.push(job);
+            for need in &scan.jobs[job].needs {
+                if let Some(cycle) = visit(scan, need, path, done) {
+                    return Some(cycle);
+                }
+            }
+            path.pop();
+            done.insert(job);
+            None
+        }
+
+        let mut done = BTreeSet::new();
+        for (name, job) in &scan.jobs {
+            if let Some(cycle) = visit(scan, name, &mut Vec::new(), &mut done) {
+                self.error(
+                    job.position,
+                    format!(
+                        "Jobs of scan '{scan_name}' form a dependency cycle: {}",
+                        cycle.join(" -> ")
+                    ),
+                );
+                // Every job on the cycle is now done, so the cycle is reported once
+                done.extend(cycle);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WORKFLOW: &str = r#"vars:
+  MODE: full
+scans:
+  subdomains:
+    cron: "0 0 * * *"
+    inputs:
+      domain:
+        type: string
+        required: true
+    jobs:
+      enumerate:
+        steps:
+          - run: subfinder -d ${{ inputs.domain }} -o subdomains.txt
+        artifacts:
+          subdomains: subdomains.txt
+      probe:
+        needs: [enumerate]
+        steps:
+          - name: probe
+            run: httpx -l subdomains.txt
+"#;
+
+    #[test]
+    fn test_parse_valid_workflow() {
+        let workflow =
+            Workflow::parse(WORKFLOW).unwrap_or_else(|err| panic!("expected ok, got {err:?}"));
+
+        assert_eq!(workflow.vars.get("MODE").map(String::as_str), Some("full"));
+        let scan = workflow.scans.get("subdomains").expect("scan to exist");
+        assert_eq!(scan.position, Position { line: 4, col: 3 });
+        assert_eq!(scan.cron.as_deref(), Some("0 0 * * *"));
+        assert!(scan.inputs["domain"].required);
+        assert_eq!(scan.jobs["probe"].needs, vec!["enumerate".to_string()]);
+        assert_eq!(
+            workflow.job_order("subdomains"),
+            Some(vec!["enumerate", "probe"])
+        );
+        assert_eq!(workflow.job_order("missing"), None);
+    }
+
+    #[test]
+    fn test_parse_reports_positions() {
+        let source = r#"scans:
+  bad-name:
+    inputs:
+      "bad key":
+        type: string
+    jobs:
+      a:
+        needs: [b]
+        steps:
+          - run: echo ${{ inputs. }}
+      b:
+        needs: [a, missing]
+        steps:
+          - run: echo
+"#;
+        let diagnostics = Workflow::parse(source).expect_err("expected error");
+        let lines = diagnostics
+            .iter()
+            .map(|d| d.to_string())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            lines,
+            vec![
+                "2:3: Invalid scan name: 'bad-name'",
+                "4:7: Input key 'bad key' is in invalid format",
+                "7:7: Jobs of scan 'bad-name' form a dependency cycle: a -> b -> a",
+                "10:18: Expected property name, found end of expression",
+                "12:20: Job 'b' needs unknown job 'missing'",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_structural_changes() {
+        let from = Workflow::parse(WORKFLOW).expect("expected ok");
+        let mut to = from.clone();
+        to.scans
+            .get_mut("subdomains")
+            .expect("scan to exist")
+            .inputs
+            .clear();
+        to.scans
+            .get_mut("subdomains")
+            .expect("scan to exist")
+            .jobs
+            .remove("probe");
+        to.scans.insert("ports".to_string(), Scan::default());
+
+        assert_eq!(
+            from.structural_changes(&to),
+            vec![
+                "+ scan ports",
+                "- job subdomains.probe",
+                "- input subdomains.domain",
+            ]
+        );
+        assert!(from.structural_changes(&from).is_empty());
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let workflow = Workflow::parse(WORKFLOW).expect("expected ok");
+        assert_eq!(
+            workflow.to_dot(),
+            r#"digraph workflow {
+  subgraph "cluster_subdomains" {
+    label="subdomains";
+    "subdomains.enumerate" [label="enumerate"];
+    "subdomains.probe" [label="probe"];
+  }
+  "subdomains.enumerate" -> "subdomains.probe";
+}
+"#
+        );
+    }
+
+    #[test]
+    fn test_to_mermaid() {
+        let workflow = Workflow::parse(WORKFLOW).expect("expected ok");
+        assert_eq!(
+            workflow.to_mermaid(),
+            r#"flowchart LR
+  subgraph s0 ["subdomains"]
+    n0["enumerate"]
+    n1["probe"]
+  end
+  n0 --> n1
+"#
+        );
+    }
+
+    #[test]
+    fn test_parse_syntax_error() {
+        let diagnostics = Workflow::parse("scans:\n  a: [\n").expect_err("expected error");
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].position.line, 3);
+    }
+}